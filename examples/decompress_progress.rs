@@ -1,7 +1,26 @@
-use std::{fs::File, io::Write, path::PathBuf};
+use sevenz_rust::{Progress, SevenZArchiveEntry};
+
+struct PrintProgress {
+    total_size: u64,
+    uncompressed_size: u64,
+}
+
+impl Progress for PrintProgress {
+    fn entry_started(&mut self, entry: &SevenZArchiveEntry) {
+        println!("extracting:{}", entry.name());
+    }
+
+    fn bytes_processed(&mut self, unpacked: u64, _packed: u64) {
+        self.uncompressed_size += unpacked;
+        println!(
+            "progress:{:.2}%",
+            (self.uncompressed_size as f64 / self.total_size as f64) * 100f64
+        );
+    }
+}
 
 fn main() {
-    let mut sz = sevenz_rust::SevenZReader::open("examples/data/sample.7z", "pass".into()).unwrap();
+    let sz = sevenz_rust::SevenZReader::open("examples/data/sample.7z", "pass".into()).unwrap();
     let total_size: u64 = sz
         .archive()
         .files
@@ -9,25 +28,17 @@ fn main() {
         .filter(|e| e.has_stream())
         .map(|e| e.size())
         .sum();
-    let mut uncompressed_size = 0;
-    let dest = PathBuf::from("examples/data/sample");
-    sz.for_each_entries(|entry, reader| {
-        let mut buf = [0u8; 1024];
-        let path = dest.join(entry.name());
-        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
-        let mut file = File::create(path).unwrap();
-        loop {
-            let read_size = reader.read(&mut buf)?;
-            if read_size == 0 {
-                break Ok(true);
-            }
-            file.write_all(&buf[..read_size])?;
-            uncompressed_size += read_size;
-            println!(
-                "progress:{:.2}%",
-                (uncompressed_size as f64 / total_size as f64) * 100f64
-            );
-        }
-    })
+    let progress = PrintProgress {
+        total_size,
+        uncompressed_size: 0,
+    };
+    sevenz_rust::decompress_file_with_progress(
+        "examples/data/sample.7z",
+        "examples/data/sample",
+        "pass".into(),
+        sevenz_rust::ReaderOptions::default(),
+        progress,
+        None,
+    )
     .unwrap();
 }
@@ -0,0 +1,262 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    io::{ErrorKind, Write},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+};
+
+use super::{CountingWriter, LZMA2Options, LZMA2Writer};
+
+const BLOCK_SIZE_MIN: u64 = 1 << 20;
+
+type BlockResult = (u64, std::io::Result<Vec<u8>>);
+
+/// Options of [LZMA2MtWriter].
+#[derive(Debug, Clone)]
+pub struct LZMA2MtOptions {
+    /// Options of the LZMA2 encoder of each block.
+    pub lzma2: LZMA2Options,
+    /// Size of the independently compressed blocks.
+    /// `None` uses 3 times the dictionary size, but at least 1 MiB.
+    pub block_size: Option<u64>,
+    /// Number of threads compressing the blocks.
+    pub threads: u32,
+}
+
+impl LZMA2MtOptions {
+    /// Blocks of the default size compressed with `lzma2` on `threads` threads.
+    pub fn new(lzma2: LZMA2Options, threads: u32) -> Self {
+        Self {
+            lzma2,
+            block_size: None,
+            threads,
+        }
+    }
+}
+
+impl From<LZMA2Options> for LZMA2MtOptions {
+    fn from(lzma2: LZMA2Options) -> Self {
+        Self::new(lzma2, 1)
+    }
+}
+
+/// LZMA2 format writer that compresses blocks of [LZMA2MtOptions::block_size] bytes on several threads.
+///
+/// Each block starts with a dictionary reset, so blocks don't depend on each other and
+/// the output is a regular LZMA2 stream that [crate::LZMA2Reader] can decode.
+/// The output only depends on the options, not on the number of threads.
+/// # Examples
+/// ```
+/// use std::io::Write;
+/// use lzma_rust::{CountingWriter, LZMA2MtOptions, LZMA2MtWriter, LZMA2Options};
+/// let mut out = Vec::new();
+/// let mut options = LZMA2MtOptions::new(LZMA2Options::with_preset(1), 4);
+/// options.block_size = Some(1 << 20);
+/// {
+///     let mut writer = LZMA2MtWriter::new(CountingWriter::new(&mut out), &options);
+///     writer.write_all(b"hello world").unwrap();
+///     writer.finish().unwrap();
+/// }
+/// ```
+pub struct LZMA2MtWriter<W: Write> {
+    inner: CountingWriter<W>,
+    options: Arc<LZMA2Options>,
+    block_size: usize,
+    max_pending: usize,
+    buf: Vec<u8>,
+    /// `None` when the blocks are compressed on the calling thread.
+    job_tx: Option<Sender<(u64, Vec<u8>)>>,
+    result_rx: Receiver<BlockResult>,
+    workers: Vec<JoinHandle<()>>,
+    /// Index of the next block to hand to the workers.
+    next_block: u64,
+    /// Index of the next block to write to `inner`.
+    next_write: u64,
+    /// Compressed blocks that are waiting for earlier blocks to be written.
+    done: BTreeMap<u64, Vec<u8>>,
+    finished: bool,
+}
+
+impl<W: Write> LZMA2MtWriter<W> {
+    /// Creates a writer using [LZMA2MtOptions::threads] worker threads.
+    ///
+    /// Up to `2 * threads` uncompressed blocks are buffered in memory, and each thread
+    /// has its own encoder whose match finder takes several times `dict_size`.
+    /// With the default block size of 3 times the dictionary, count more than
+    /// 10 times `dict_size` per thread.
+    ///
+    /// With one thread, or on wasm32 where threads can't be spawned,
+    /// the blocks are compressed on the calling thread.
+    pub fn new(inner: CountingWriter<W>, options: &LZMA2MtOptions) -> Self {
+        let threads = if cfg!(target_arch = "wasm32") {
+            1
+        } else {
+            options.threads.max(1) as usize
+        };
+        let block_size = options
+            .block_size
+            .unwrap_or((options.lzma2.dict_size as u64 * 3).max(BLOCK_SIZE_MIN))
+            .clamp(1, u32::MAX as u64) as usize;
+        let (job_tx, job_rx) = channel::<(u64, Vec<u8>)>();
+        let (result_tx, result_rx) = channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let options = Arc::new(options.lzma2.clone());
+        // a single thread compresses the blocks in `send_block`
+        let spawned = if threads > 1 { threads } else { 0 };
+        let workers = (0..spawned)
+            .map(|_| {
+                let job_rx = Arc::clone(&job_rx);
+                let result_tx = result_tx.clone();
+                let options = Arc::clone(&options);
+                std::thread::spawn(move || loop {
+                    let job = job_rx.lock().unwrap().recv();
+                    let (index, data) = match job {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    let result = encode_block(&data, &options, index == 0);
+                    if result_tx.send((index, result)).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+        Self {
+            inner,
+            options,
+            block_size,
+            max_pending: threads * 2,
+            buf: Vec::new(),
+            job_tx: if spawned > 0 { Some(job_tx) } else { None },
+            result_rx,
+            workers,
+            next_block: 0,
+            next_write: 0,
+            done: BTreeMap::new(),
+            finished: false,
+        }
+    }
+
+    fn send_block(&mut self) -> std::io::Result<()> {
+        while self.next_block - self.next_write >= self.max_pending as u64 {
+            self.receive_block()?;
+        }
+        let data = std::mem::take(&mut self.buf);
+        let tx = match &self.job_tx {
+            Some(tx) => tx,
+            None => {
+                let block = encode_block(&data, &self.options, self.next_block == 0)?;
+                self.inner.write_all(&block)?;
+                self.next_block += 1;
+                self.next_write += 1;
+                return Ok(());
+            }
+        };
+        if tx.send((self.next_block, data)).is_err() {
+            return Err(std::io::Error::new(
+                ErrorKind::Other,
+                "LZMA2 worker threads stopped",
+            ));
+        }
+        self.next_block += 1;
+        Ok(())
+    }
+
+    /// Waits for one compressed block and writes all blocks that are ready in order.
+    fn receive_block(&mut self) -> std::io::Result<()> {
+        let (index, result) = self
+            .result_rx
+            .recv()
+            .map_err(|_| std::io::Error::new(ErrorKind::Other, "LZMA2 worker threads stopped"))?;
+        self.done.insert(index, result?);
+        while let Some(block) = self.done.remove(&self.next_write) {
+            self.inner.write_all(&block)?;
+            self.next_write += 1;
+        }
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        if !self.buf.is_empty() {
+            self.send_block()?;
+        }
+        while self.next_write < self.next_block {
+            self.receive_block()?;
+        }
+        self.inner.write_all(&[0x00])?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+/// Compresses `data` into LZMA2 chunks without the end marker.
+fn encode_block(data: &[u8], options: &LZMA2Options, first: bool) -> std::io::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() / 2);
+    {
+        // only the first block can use the preset dictionary, the others start with a reset
+        let options = if !first && options.preset_dict.is_some() {
+            Cow::Owned(LZMA2Options {
+                preset_dict: None,
+                ..options.clone()
+            })
+        } else {
+            Cow::Borrowed(options)
+        };
+        let mut w = LZMA2Writer::new(CountingWriter::new(&mut out), &options);
+        w.write_all(data)?;
+        w.finish()?;
+    }
+    out.pop();
+    Ok(out)
+}
+
+impl<W: Write> Drop for LZMA2MtWriter<W> {
+    fn drop(&mut self) {
+        self.job_tx = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl<W: Write> Write for LZMA2MtWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() && !self.finished {
+            self.finish()?;
+            self.inner.write(buf)?;
+            return Ok(0);
+        }
+        if self.finished {
+            return Err(std::io::Error::new(ErrorKind::Other, "LZMA2 finished"));
+        }
+        let len = buf.len().min(self.block_size - self.buf.len());
+        self.buf.extend_from_slice(&buf[..len]);
+        if self.buf.len() == self.block_size {
+            self.send_block()?;
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.finished {
+            return Err(std::io::Error::new(
+                ErrorKind::Other,
+                "LZMA2 flush finished",
+            ));
+        }
+        if !self.buf.is_empty() {
+            self.send_block()?;
+        }
+        while self.next_write < self.next_block {
+            self.receive_block()?;
+        }
+        self.inner.flush()
+    }
+}
@@ -34,9 +34,17 @@ pub(crate) const K_ENCODED_HEADER: u8 = 0x17;
 pub(crate) const K_START_POS: u8 = 0x18;
 pub(crate) const K_DUMMY: u8 = 0x19;
 
+/// Unix file type bits of a mode.
+const S_IFMT: u32 = 0o170000;
+/// Unix file type of a symbolic link.
+const S_IFLNK: u32 = 0o120000;
+
 #[derive(Debug, Default, Clone)]
 pub struct Archive {
-    /// Offset from beginning of file + SIGNATURE_HEADER_SIZE to packed streams.
+    /// Position of the signature header in the source, which is not 0 for archives
+    /// embedded after a stub like a self-extracting executable.
+    pub signature_offset: u64,
+    /// Offset from the signature header + SIGNATURE_HEADER_SIZE to packed streams.
     pub pack_pos: u64,
     pub pack_sizes: Vec<u64>,
     pub pack_crcs_defined: bit_set::BitSet,
@@ -78,6 +86,12 @@ pub struct SevenZArchiveEntry {
 }
 
 impl SevenZArchiveEntry {
+    pub const FILE_ATTRIBUTE_READONLY: u32 = 0x01;
+    pub const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+    pub const FILE_ATTRIBUTE_ARCHIVE: u32 = 0x20;
+    /// Set by p7zip and 7-Zip if the high 16 bits of the attributes hold the Unix mode.
+    pub const FILE_ATTRIBUTE_UNIX_EXTENSION: u32 = 0x8000;
+
     pub fn new() -> Self {
         Default::default()
     }
@@ -110,6 +124,46 @@ impl SevenZArchiveEntry {
         self.windows_attributes
     }
 
+    /// Returns the Unix mode (file type and permission bits) stored in the high 16 bits of
+    /// the attributes, if [Self::FILE_ATTRIBUTE_UNIX_EXTENSION] is set.
+    pub fn unix_mode(&self) -> Option<u32> {
+        if self.has_windows_attributes
+            && self.windows_attributes & Self::FILE_ATTRIBUTE_UNIX_EXTENSION != 0
+        {
+            Some(self.windows_attributes >> 16)
+        } else {
+            None
+        }
+    }
+
+    /// Stores the Unix `mode` in the attributes the way p7zip does, keeping the low 16 bits.
+    pub fn set_unix_mode(&mut self, mode: u32) {
+        let attributes = if self.has_windows_attributes {
+            self.windows_attributes & 0xFFFF
+        } else {
+            0
+        };
+        self.windows_attributes =
+            attributes | Self::FILE_ATTRIBUTE_UNIX_EXTENSION | ((mode & 0xFFFF) << 16);
+        self.has_windows_attributes = true;
+    }
+
+    /// Returns the permission bits of [Self::unix_mode], without the file type.
+    pub fn unix_permissions(&self) -> Option<u32> {
+        self.unix_mode().map(|mode| mode & 0o7777)
+    }
+
+    /// Returns `true` if the Unix mode marks the entry as a symbolic link,
+    /// whose content is then the link target.
+    pub fn is_symlink(&self) -> bool {
+        self.unix_mode()
+            .is_some_and(|mode| mode & S_IFMT == S_IFLNK)
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.has_windows_attributes && self.windows_attributes & Self::FILE_ATTRIBUTE_READONLY != 0
+    }
+
     pub fn access_date(&self) -> FileTime {
         self.access_date
     }
@@ -138,29 +192,77 @@ impl SevenZArchiveEntry {
         };
 
         if let Ok(meta) = path.metadata() {
-            if let Ok(modified) = meta.modified() {
-                if let Ok(date) = modified.try_into() {
-                    entry.last_modified_date = date;
-                    entry.has_last_modified_date = entry.last_modified_date.to_raw() > 0;
-                }
+            entry.fill_metadata(&meta);
+        }
+        entry
+    }
+
+    /// Creates an entry for the symbolic link at `path` itself, not the file it points to.
+    /// Its content must be the link target, see [Self::is_symlink].
+    pub fn from_symlink_path(
+        path: impl AsRef<std::path::Path>,
+        entry_name: String,
+    ) -> SevenZArchiveEntry {
+        let mut entry = Self::from_path(path.as_ref(), entry_name);
+        entry.has_stream = true;
+        entry.is_directory = false;
+        if let Ok(meta) = path.as_ref().symlink_metadata() {
+            entry.fill_metadata(&meta);
+        }
+        if !entry.is_symlink() {
+            entry.windows_attributes = Self::FILE_ATTRIBUTE_ARCHIVE;
+            entry.set_unix_mode(S_IFLNK | 0o777);
+        }
+        entry
+    }
+
+    fn fill_metadata(&mut self, meta: &std::fs::Metadata) {
+        if let Ok(modified) = meta.modified() {
+            if let Ok(date) = modified.try_into() {
+                self.last_modified_date = date;
+                self.has_last_modified_date = self.last_modified_date.to_raw() > 0;
             }
-            if let Ok(date) = meta.created() {
-                if let Ok(date) = date.try_into() {
-                    entry.creation_date = date;
-                    entry.has_creation_date = entry.creation_date.to_raw() > 0;
-                }
+        }
+        if let Ok(date) = meta.created() {
+            if let Ok(date) = date.try_into() {
+                self.creation_date = date;
+                self.has_creation_date = self.creation_date.to_raw() > 0;
             }
-            if let Ok(date) = meta.accessed() {
-                if let Ok(date) = date.try_into() {
-                    entry.access_date = date;
-                    entry.has_access_date = entry.access_date.to_raw() > 0;
-                }
+        }
+        if let Ok(date) = meta.accessed() {
+            if let Ok(date) = date.try_into() {
+                self.access_date = date;
+                self.has_access_date = self.access_date.to_raw() > 0;
             }
         }
-        entry
+        self.windows_attributes = attributes_of(meta);
+        self.has_windows_attributes = true;
     }
 }
 
+#[cfg(windows)]
+fn attributes_of(meta: &std::fs::Metadata) -> u32 {
+    std::os::windows::fs::MetadataExt::file_attributes(meta)
+}
+
+#[cfg(not(windows))]
+fn attributes_of(meta: &std::fs::Metadata) -> u32 {
+    let mut attributes = if meta.is_dir() {
+        SevenZArchiveEntry::FILE_ATTRIBUTE_DIRECTORY
+    } else {
+        SevenZArchiveEntry::FILE_ATTRIBUTE_ARCHIVE
+    };
+    if meta.permissions().readonly() {
+        attributes |= SevenZArchiveEntry::FILE_ATTRIBUTE_READONLY;
+    }
+    #[cfg(unix)]
+    {
+        let mode = std::os::unix::fs::MetadataExt::mode(meta);
+        attributes |= SevenZArchiveEntry::FILE_ATTRIBUTE_UNIX_EXTENSION | ((mode & 0xFFFF) << 16);
+    }
+    attributes
+}
+
 #[derive(Debug, Default)]
 pub struct SevenZMethodConfiguration {
     pub method: SevenZMethod,
@@ -269,6 +371,22 @@ impl SevenZMethod {
         self.1
     }
 
+    /// Whether it is a filter that `SevenZWriter` applies before the
+    /// compression, like BCJ or delta. `BCJ_IA64` can only be decoded, so it is not one.
+    #[inline]
+    pub fn is_filter(&self) -> bool {
+        [
+            Self::ID_BCJ_X86,
+            Self::ID_BCJ_PPC,
+            Self::ID_BCJ_ARM,
+            Self::ID_BCJ_ARM_THUMB,
+            Self::ID_BCJ_SPARC,
+            Self::ID_DELTA,
+            Self::ID_BCJ2,
+        ]
+        .contains(&self.id())
+    }
+
     #[inline]
     pub fn by_id(id: &[u8]) -> Option<Self> {
         Self::METHODS
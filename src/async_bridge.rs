@@ -0,0 +1,54 @@
+//! Runs the blocking reader and writer on a helper thread while the async side does the I/O.
+//!
+//! The helper thread sends requests like "read `len` bytes at `pos`" through a bounded tokio
+//! channel and blocks until the async side has answered them, so the codec stack is the same
+//! as for the blocking API and only the requested bytes are buffered.
+use std::io::ErrorKind;
+
+use tokio::sync::mpsc;
+
+/// The number of messages that the helper thread can send ahead of the async side.
+const CHANNEL_SIZE: usize = 4;
+
+pub(crate) type Response = std::io::Result<Vec<u8>>;
+
+/// The helper thread side of the channels.
+pub(crate) struct Bridge<M> {
+    tx: mpsc::Sender<M>,
+    rx: std::sync::mpsc::Receiver<Response>,
+}
+
+/// Creates the channels between a helper thread and the async side.
+pub(crate) fn channel<M>() -> (
+    Bridge<M>,
+    mpsc::Receiver<M>,
+    std::sync::mpsc::Sender<Response>,
+) {
+    let (tx, msg_rx) = mpsc::channel(CHANNEL_SIZE);
+    let (resp_tx, rx) = std::sync::mpsc::channel();
+    (Bridge { tx, rx }, msg_rx, resp_tx)
+}
+
+pub(crate) fn stopped() -> std::io::Error {
+    std::io::Error::new(
+        ErrorKind::Other,
+        "the other side of the async bridge stopped",
+    )
+}
+
+impl<M> Bridge<M> {
+    pub fn sender(&self) -> mpsc::Sender<M> {
+        self.tx.clone()
+    }
+
+    /// Sends a message that needs no response.
+    pub fn send(&self, msg: M) -> std::io::Result<()> {
+        self.tx.blocking_send(msg).map_err(|_| stopped())
+    }
+
+    /// Sends a request and waits for its response.
+    pub fn request(&self, msg: M) -> Response {
+        self.send(msg)?;
+        self.rx.recv().map_err(|_| stopped())?
+    }
+}
@@ -0,0 +1,386 @@
+use std::{
+    future::poll_fn,
+    io::{ErrorKind, Read, Seek, SeekFrom},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncSeek, AsyncSeekExt, ReadBuf},
+    sync::mpsc,
+};
+
+use crate::{
+    async_bridge::{self, stopped, Bridge, Response},
+    Archive, Error, Password, ReaderOptions, SevenZArchiveEntry, SevenZReader,
+};
+
+/// The minimum number of bytes requested from the async source at once.
+const READ_AHEAD: usize = 64 * 1024;
+/// The maximum size of the chunks of entry data sent to the async side.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+enum Msg {
+    Read { pos: u64, len: usize },
+    Header(Result<Box<Archive>, Error>),
+    Entry(Box<SevenZArchiveEntry>),
+    Data(Vec<u8>),
+    EntryEnd,
+    Done(Result<(), Error>),
+}
+
+/// A [Read] + [Seek] source on the helper thread that requests its bytes from the async side.
+struct RemoteSource {
+    bridge: Bridge<Msg>,
+    pos: u64,
+    len: u64,
+    cache_pos: u64,
+    cache: Vec<u8>,
+}
+
+impl RemoteSource {
+    fn new(bridge: Bridge<Msg>, len: u64) -> Self {
+        Self {
+            bridge,
+            pos: 0,
+            len,
+            cache_pos: 0,
+            cache: Vec::new(),
+        }
+    }
+}
+
+impl Read for RemoteSource {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let cache_end = self.cache_pos + self.cache.len() as u64;
+        if self.pos < self.cache_pos || self.pos >= cache_end {
+            let len = (buf.len().max(READ_AHEAD) as u64).min(self.len - self.pos) as usize;
+            self.cache = self.bridge.request(Msg::Read { pos: self.pos, len })?;
+            self.cache_pos = self.pos;
+            if self.cache.is_empty() {
+                return Ok(0);
+            }
+        }
+        let start = (self.pos - self.cache_pos) as usize;
+        let size = buf.len().min(self.cache.len() - start);
+        buf[..size].copy_from_slice(&self.cache[start..start + size]);
+        self.pos += size as u64;
+        Ok(size)
+    }
+}
+
+impl Seek for RemoteSource {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(pos) => self.len.checked_add_signed(pos),
+            SeekFrom::Current(pos) => self.pos.checked_add_signed(pos),
+        };
+        self.pos = new_pos
+            .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidInput, "SeekBeforeStart"))?;
+        Ok(self.pos)
+    }
+}
+
+enum ReadState {
+    Seek,
+    Seeking,
+    Reading,
+}
+
+/// A read request of the helper thread that is being served.
+struct Serving {
+    pos: u64,
+    buf: Vec<u8>,
+    filled: usize,
+    state: ReadState,
+}
+
+/// The async side of the channels, serving the read requests from `source`.
+struct Driver<'a, R> {
+    source: &'a mut R,
+    rx: mpsc::Receiver<Msg>,
+    resp: std::sync::mpsc::Sender<Response>,
+    serving: Option<Serving>,
+}
+
+impl<'a, R: AsyncRead + AsyncSeek + Unpin> Driver<'a, R> {
+    fn spawn(source: &'a mut R, run: impl FnOnce(Bridge<Msg>) + Send + 'static) -> Self {
+        let (bridge, rx, resp) = async_bridge::channel();
+        std::thread::spawn(move || run(bridge));
+        Self {
+            source,
+            rx,
+            resp,
+            serving: None,
+        }
+    }
+
+    fn poll_serving(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<Vec<u8>>> {
+        let serving = self.serving.as_mut().unwrap();
+        let mut source = Pin::new(&mut *self.source);
+        loop {
+            match serving.state {
+                ReadState::Seek => {
+                    source.as_mut().start_seek(SeekFrom::Start(serving.pos))?;
+                    serving.state = ReadState::Seeking;
+                }
+                ReadState::Seeking => {
+                    ready!(source.as_mut().poll_complete(cx))?;
+                    serving.state = ReadState::Reading;
+                }
+                ReadState::Reading => {
+                    if serving.filled == serving.buf.len() {
+                        return Poll::Ready(Ok(std::mem::take(&mut serving.buf)));
+                    }
+                    let mut buf = ReadBuf::new(&mut serving.buf[serving.filled..]);
+                    ready!(source.as_mut().poll_read(cx, &mut buf))?;
+                    let size = buf.filled().len();
+                    if size == 0 {
+                        serving.buf.truncate(serving.filled);
+                    }
+                    serving.filled += size;
+                }
+            }
+        }
+    }
+
+    /// Serves read requests until the helper thread sends another message.
+    /// Returns `None` if the helper thread stopped.
+    fn poll_msg(&mut self, cx: &mut Context<'_>) -> Poll<Option<Msg>> {
+        loop {
+            if self.serving.is_some() {
+                let result = ready!(self.poll_serving(cx));
+                self.serving = None;
+                // the helper thread stops with an error if it is gone
+                let _ = self.resp.send(result);
+            }
+            match ready!(self.rx.poll_recv(cx)) {
+                Some(Msg::Read { pos, len }) => {
+                    self.serving = Some(Serving {
+                        pos,
+                        buf: vec![0; len],
+                        filled: 0,
+                        state: ReadState::Seek,
+                    })
+                }
+                msg => return Poll::Ready(msg),
+            }
+        }
+    }
+
+    async fn next_msg(&mut self) -> Option<Msg> {
+        poll_fn(|cx| self.poll_msg(cx)).await
+    }
+}
+
+/// Reads 7z archives from an [AsyncRead] + [AsyncSeek] source.
+///
+/// The header and the entries are decoded by the same codecs as [SevenZReader],
+/// running on a helper thread that requests the bytes it needs from the async source,
+/// so decoding doesn't block the async runtime.
+/// # Examples
+/// ```no_run
+/// # async fn run() -> Result<(), sevenz_rust::Error> {
+/// use sevenz_rust::*;
+/// use tokio::io::AsyncReadExt;
+/// let file = tokio::fs::File::open("example.7z").await?;
+/// let mut sz = AsyncSevenZReader::new(file, Password::empty()).await?;
+/// let mut entries = sz.entries();
+/// while let Some(mut entry) = entries.next_entry().await? {
+///     let mut content = Vec::new();
+///     entry.read_to_end(&mut content).await?;
+///     println!("{}: {} bytes", entry.entry().name(), content.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncSevenZReader<R> {
+    source: R,
+    len: u64,
+    archive: Archive,
+    password: Password,
+    options: ReaderOptions,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncSevenZReader<R> {
+    pub async fn new(source: R, password: Password) -> Result<Self, Error> {
+        Self::new_with_options(source, password, ReaderOptions::default()).await
+    }
+
+    /// Like [new](Self::new), with options to limit the resources used for untrusted archives.
+    pub async fn new_with_options(
+        mut source: R,
+        password: Password,
+        options: ReaderOptions,
+    ) -> Result<Self, Error> {
+        let len = source.seek(SeekFrom::End(0)).await.map_err(Error::io)?;
+        let password_bytes = password.as_slice().to_vec();
+        let mut driver = Driver::spawn(&mut source, move |bridge| {
+            let sender = bridge.sender();
+            let mut remote = RemoteSource::new(bridge, len);
+            let result = Archive::read_with_options(&mut remote, len, &password_bytes, options);
+            let _ = sender.blocking_send(Msg::Header(result.map(Box::new)));
+        });
+        let archive = match driver.next_msg().await {
+            Some(Msg::Header(result)) => *result?,
+            _ => return Err(Error::io(stopped())),
+        };
+        Ok(Self {
+            source,
+            len,
+            archive,
+            password,
+            options,
+        })
+    }
+
+    #[inline]
+    pub fn archive(&self) -> &Archive {
+        &self.archive
+    }
+
+    /// Returns a cursor over the entries in archive order, decoding them on a helper thread.
+    pub fn entries(&mut self) -> AsyncEntries<'_, R> {
+        let archive = self.archive.clone();
+        let password = self.password.clone();
+        let options = self.options;
+        let len = self.len;
+        let driver = Driver::spawn(&mut self.source, move |bridge| {
+            let sender = bridge.sender();
+            let remote = RemoteSource::new(bridge, len);
+            let mut sz =
+                SevenZReader::from_archive(archive, remote, password).with_options(options);
+            let mut buf = vec![0; CHUNK_SIZE];
+            let result = sz.for_each_entries(|entry, reader| {
+                let send = |msg| sender.blocking_send(msg).map_err(|_| Error::io(stopped()));
+                send(Msg::Entry(Box::new(entry.clone())))?;
+                loop {
+                    let size = reader.read(&mut buf)?;
+                    if size == 0 {
+                        break;
+                    }
+                    send(Msg::Data(buf[..size].to_vec()))?;
+                }
+                send(Msg::EntryEnd)?;
+                Ok(true)
+            });
+            let _ = sender.blocking_send(Msg::Done(result));
+        });
+        AsyncEntries {
+            driver,
+            entry: None,
+            data: Vec::new(),
+            data_pos: 0,
+            entry_done: true,
+            finished: false,
+        }
+    }
+}
+
+/// The entries of an [AsyncSevenZReader], see [AsyncSevenZReader::entries].
+///
+/// Dropping it stops the helper thread.
+pub struct AsyncEntries<'a, R> {
+    driver: Driver<'a, R>,
+    entry: Option<Box<SevenZArchiveEntry>>,
+    data: Vec<u8>,
+    data_pos: usize,
+    entry_done: bool,
+    finished: bool,
+}
+
+impl<'a, R: AsyncRead + AsyncSeek + Unpin> AsyncEntries<'a, R> {
+    /// Skips the rest of the current entry and returns a reader for the next one,
+    /// or `None` after the last entry.
+    pub async fn next_entry(&mut self) -> Result<Option<AsyncEntryReader<'_, 'a, R>>, Error> {
+        if self.finished {
+            return Ok(None);
+        }
+        loop {
+            match self.driver.next_msg().await {
+                Some(Msg::Entry(entry)) => {
+                    self.entry = Some(entry);
+                    self.data.clear();
+                    self.data_pos = 0;
+                    self.entry_done = false;
+                    return Ok(Some(AsyncEntryReader { entries: self }));
+                }
+                Some(Msg::Done(result)) => {
+                    self.finished = true;
+                    return result.map(|_| None);
+                }
+                Some(_) => {}
+                None => {
+                    self.finished = true;
+                    return Err(Error::io(stopped()));
+                }
+            }
+        }
+    }
+
+    fn poll_read_entry(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        loop {
+            if self.data_pos < self.data.len() {
+                let size = buf.remaining().min(self.data.len() - self.data_pos);
+                buf.put_slice(&self.data[self.data_pos..self.data_pos + size]);
+                self.data_pos += size;
+                return Poll::Ready(Ok(()));
+            }
+            if self.entry_done {
+                return Poll::Ready(Ok(()));
+            }
+            match ready!(self.driver.poll_msg(cx)) {
+                Some(Msg::Data(data)) => {
+                    self.data = data;
+                    self.data_pos = 0;
+                }
+                Some(Msg::EntryEnd) => self.entry_done = true,
+                Some(Msg::Done(result)) => {
+                    self.entry_done = true;
+                    self.finished = true;
+                    let e = match result {
+                        Err(e) => std::io::Error::new(ErrorKind::Other, e),
+                        Ok(()) => std::io::Error::from(ErrorKind::UnexpectedEof),
+                    };
+                    return Poll::Ready(Err(e));
+                }
+                Some(_) => {}
+                None => {
+                    self.entry_done = true;
+                    self.finished = true;
+                    return Poll::Ready(Err(stopped()));
+                }
+            }
+        }
+    }
+}
+
+/// Reads the decoded content of one entry, see [AsyncEntries::next_entry].
+/// Checksum and decoding errors are returned by the reads.
+pub struct AsyncEntryReader<'b, 'a, R> {
+    entries: &'b mut AsyncEntries<'a, R>,
+}
+
+impl<'b, 'a, R> AsyncEntryReader<'b, 'a, R> {
+    pub fn entry(&self) -> &SevenZArchiveEntry {
+        self.entries.entry.as_ref().unwrap()
+    }
+}
+
+impl<'b, 'a, R: AsyncRead + AsyncSeek + Unpin> AsyncRead for AsyncEntryReader<'b, 'a, R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        self.get_mut().entries.poll_read_entry(cx, buf)
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    io::{ErrorKind, Read, Seek, SeekFrom, Write},
+    rc::Rc,
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt},
+    sync::mpsc,
+};
+
+use crate::{
+    async_bridge::{self, stopped, Bridge, Response},
+    Error, SevenZArchiveEntry, SevenZMethodConfiguration, SevenZWriter,
+};
+
+/// The number of bytes the helper thread buffers before sending them to the async output.
+const WRITE_BUFFER_SIZE: usize = 64 * 1024;
+/// The maximum number of bytes requested from an entry reader at once.
+const READ_SIZE: usize = 64 * 1024;
+
+enum Msg {
+    Write { pos: u64, data: Vec<u8> },
+    ReadInput { len: usize },
+    Pushed(Result<SevenZArchiveEntry, Error>),
+    Finished(Result<(), Error>),
+}
+
+enum Cmd {
+    ContentMethods(Vec<SevenZMethodConfiguration>),
+    EncryptHeader(bool),
+    Push(SevenZArchiveEntry, bool),
+    Finish,
+}
+
+/// A [Write] + [Seek] output on the helper thread that sends its bytes to the async side.
+struct RemoteSink {
+    bridge: Rc<Bridge<Msg>>,
+    pos: u64,
+    len: u64,
+    buf_pos: u64,
+    buf: Vec<u8>,
+}
+
+impl Write for RemoteSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.pos != self.buf_pos + self.buf.len() as u64 {
+            self.flush()?;
+            self.buf_pos = self.pos;
+        }
+        self.buf.extend_from_slice(buf);
+        self.pos += buf.len() as u64;
+        self.len = self.len.max(self.pos);
+        if self.buf.len() >= WRITE_BUFFER_SIZE {
+            self.flush()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.buf.is_empty() {
+            let data = std::mem::take(&mut self.buf);
+            let pos = self.buf_pos;
+            self.buf_pos += data.len() as u64;
+            self.bridge.request(Msg::Write { pos, data })?;
+        }
+        Ok(())
+    }
+}
+
+impl Seek for RemoteSink {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(pos) => self.len.checked_add_signed(pos),
+            SeekFrom::Current(pos) => self.pos.checked_add_signed(pos),
+        };
+        self.pos = new_pos
+            .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidInput, "SeekBeforeStart"))?;
+        Ok(self.pos)
+    }
+}
+
+/// The content of the entry being pushed, requested from the async reader.
+struct InputReader {
+    bridge: Rc<Bridge<Msg>>,
+}
+
+impl Read for InputReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(READ_SIZE);
+        let data = self.bridge.request(Msg::ReadInput { len })?;
+        buf[..data.len()].copy_from_slice(&data);
+        Ok(data.len())
+    }
+}
+
+fn run(bridge: Bridge<Msg>, cmds: std::sync::mpsc::Receiver<Cmd>) {
+    let sender = bridge.sender();
+    let bridge = Rc::new(bridge);
+    let sink = RemoteSink {
+        bridge: bridge.clone(),
+        pos: 0,
+        len: 0,
+        buf_pos: 0,
+        buf: Vec::new(),
+    };
+    // only seeks the sink, the async side reports the stopped thread if it fails anyway
+    let Ok(mut writer) = SevenZWriter::new(sink) else {
+        return;
+    };
+    while let Ok(cmd) = cmds.recv() {
+        match cmd {
+            Cmd::ContentMethods(methods) => {
+                writer.set_content_methods(methods);
+            }
+            Cmd::EncryptHeader(enabled) => writer.set_encrypt_header(enabled),
+            Cmd::Push(entry, has_reader) => {
+                let reader = has_reader.then(|| InputReader {
+                    bridge: bridge.clone(),
+                });
+                let result = writer.push_archive_entry(entry, reader).cloned();
+                if sender.blocking_send(Msg::Pushed(result)).is_err() {
+                    return;
+                }
+            }
+            Cmd::Finish => {
+                let result = writer
+                    .finish()
+                    .and_then(|mut sink| sink.flush())
+                    .map_err(Error::io);
+                let _ = sender.blocking_send(Msg::Finished(result));
+                return;
+            }
+        }
+    }
+}
+
+/// Writes 7z archives to an [AsyncWrite] + [AsyncSeek] output.
+///
+/// Entries are compressed by a [SevenZWriter] on a helper thread,
+/// which requests the entry contents from the async readers and sends the compressed
+/// bytes back to be written to the async output, so compressing doesn't block the async runtime.
+///
+/// # Cancel safety
+/// [push_archive_entry](Self::push_archive_entry) is not cancel safe: the helper thread
+/// keeps compressing the entry whose reader was dropped, so once a call is dropped before
+/// it completes, the writer is poisoned and the following calls fail.
+/// # Examples
+/// ```no_run
+/// # async fn run() -> Result<(), sevenz_rust::Error> {
+/// use sevenz_rust::*;
+/// let output = tokio::fs::File::create("dest.7z").await?;
+/// let mut sz = AsyncSevenZWriter::new(output);
+/// let source = tokio::fs::File::open("source.txt").await?;
+/// let mut entry = SevenZArchiveEntry::new();
+/// entry.name = "source.txt".to_string();
+/// sz.push_archive_entry(entry, Some(source)).await?;
+/// sz.finish().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncSevenZWriter<W> {
+    output: W,
+    /// Position of the cursor of `output`, if known.
+    pos: Option<u64>,
+    cmds: std::sync::mpsc::Sender<Cmd>,
+    rx: mpsc::Receiver<Msg>,
+    resp: std::sync::mpsc::Sender<Response>,
+    /// Set while a call talks to the helper thread, still set if that call was dropped.
+    poisoned: bool,
+}
+
+impl<W: AsyncWrite + AsyncSeek + Unpin> AsyncSevenZWriter<W> {
+    /// Prepares writer to write a 7z archive to `output`.
+    pub fn new(output: W) -> Self {
+        let (bridge, rx, resp) = async_bridge::channel();
+        let (cmds, cmd_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || run(bridge, cmd_rx));
+        Self {
+            output,
+            pos: None,
+            cmds,
+            rx,
+            resp,
+            poisoned: false,
+        }
+    }
+
+    /// See [SevenZWriter::set_content_methods].
+    pub fn set_content_methods(
+        &mut self,
+        content_methods: Vec<SevenZMethodConfiguration>,
+    ) -> &mut Self {
+        let _ = self.cmds.send(Cmd::ContentMethods(content_methods));
+        self
+    }
+
+    /// See [SevenZWriter::set_encrypt_header].
+    pub fn set_encrypt_header(&mut self, enabled: bool) {
+        let _ = self.cmds.send(Cmd::EncryptHeader(enabled));
+    }
+
+    /// Adds an archive `entry` with data from `reader`, see [SevenZWriter::push_archive_entry].
+    pub async fn push_archive_entry<R: AsyncRead + Unpin>(
+        &mut self,
+        entry: SevenZArchiveEntry,
+        mut reader: Option<R>,
+    ) -> Result<SevenZArchiveEntry, Error> {
+        self.check_poisoned()?;
+        self.cmds
+            .send(Cmd::Push(entry, reader.is_some()))
+            .map_err(|_| Error::io(stopped()))?;
+        self.poisoned = true;
+        loop {
+            match self.rx.recv().await {
+                Some(Msg::ReadInput { len }) => {
+                    let mut buf = vec![0; len];
+                    let result = match &mut reader {
+                        Some(r) => r.read(&mut buf).await.map(|size| {
+                            buf.truncate(size);
+                            buf
+                        }),
+                        None => Ok(Vec::new()),
+                    };
+                    let _ = self.resp.send(result);
+                }
+                Some(Msg::Pushed(result)) => {
+                    self.poisoned = false;
+                    return result;
+                }
+                Some(msg) => self.serve(msg).await?,
+                None => return Err(Error::io(stopped())),
+            }
+        }
+    }
+
+    /// Finishes the compression and returns the output.
+    ///
+    /// Dropping the returned future before it completes leaves the output incomplete.
+    pub async fn finish(mut self) -> Result<W, Error> {
+        self.check_poisoned()?;
+        self.cmds
+            .send(Cmd::Finish)
+            .map_err(|_| Error::io(stopped()))?;
+        loop {
+            match self.rx.recv().await {
+                Some(Msg::Finished(result)) => {
+                    result?;
+                    self.output.flush().await.map_err(Error::io)?;
+                    return Ok(self.output);
+                }
+                Some(msg) => self.serve(msg).await?,
+                None => return Err(Error::io(stopped())),
+            }
+        }
+    }
+
+    fn check_poisoned(&self) -> Result<(), Error> {
+        if self.poisoned {
+            return Err(Error::other(
+                "a previous call of the async writer was cancelled",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Writes the bytes of a [Msg::Write] to the output and answers it.
+    async fn serve(&mut self, msg: Msg) -> Result<(), Error> {
+        match msg {
+            Msg::Write { pos, data } => {
+                let result = self.write_at(pos, &data).await;
+                let _ = self.resp.send(result.map(|_| Vec::new()));
+                Ok(())
+            }
+            _ => Err(Error::other(
+                "unexpected message from the async writer thread",
+            )),
+        }
+    }
+
+    async fn write_at(&mut self, pos: u64, data: &[u8]) -> std::io::Result<()> {
+        if self.pos != Some(pos) {
+            self.pos = None;
+            self.output.seek(SeekFrom::Start(pos)).await?;
+        }
+        self.output.write_all(data).await?;
+        self.pos = Some(pos + data.len() as u64);
+        Ok(())
+    }
+}
@@ -1,9 +1,14 @@
 mod arm;
 mod ppc;
 mod sparc;
+#[cfg(feature = "compress")]
+mod writer;
 mod x86;
 use std::io::Read;
 
+#[cfg(feature = "compress")]
+pub use self::writer::SimpleWriter;
+
 pub struct BCJFilter {
     is_encoder: bool,
     pos: usize,
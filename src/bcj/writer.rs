@@ -0,0 +1,114 @@
+use std::io::Write;
+
+use super::*;
+
+/// Applies a BCJ filter to the data written to it, the encoding counterpart of [SimpleReader].
+///
+/// Writing an empty buffer finishes the filter: the trailing bytes that are too short to be
+/// filtered are written out unchanged and the empty write is passed on to `inner`.
+pub struct SimpleWriter<W> {
+    inner: W,
+    filter: BCJFilter,
+    filter_buf: Vec<u8>,
+    pos: usize,
+    unfiltered: usize,
+    finished: bool,
+}
+
+impl<W> SimpleWriter<W> {
+    fn new(inner: W, filter: BCJFilter) -> Self {
+        Self {
+            inner,
+            filter,
+            filter_buf: vec![0; FILTER_BUF_SIZE],
+            pos: 0,
+            unfiltered: 0,
+            finished: false,
+        }
+    }
+    #[inline]
+    pub fn new_x86(inner: W) -> Self {
+        Self::new(inner, BCJFilter::new_x86(0, true))
+    }
+
+    #[inline]
+    pub fn new_arm(inner: W) -> Self {
+        Self::new(inner, BCJFilter::new_arm(0, true))
+    }
+    #[inline]
+    pub fn new_arm_thumb(inner: W) -> Self {
+        Self::new(inner, BCJFilter::new_arm_thumb(0, true))
+    }
+    #[inline]
+    pub fn new_ppc(inner: W) -> Self {
+        Self::new(inner, BCJFilter::new_power_pc(0, true))
+    }
+    #[inline]
+    pub fn new_sparc(inner: W) -> Self {
+        Self::new(inner, BCJFilter::new_sparc(0, true))
+    }
+}
+
+impl<W: Write> SimpleWriter<W> {
+    fn finish(&mut self) -> std::io::Result<()> {
+        if !self.finished {
+            let start = self.pos;
+            self.inner
+                .write_all(&self.filter_buf[start..(start + self.unfiltered)])?;
+            self.pos = 0;
+            self.unfiltered = 0;
+            self.finished = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for SimpleWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            self.finish()?;
+            self.inner.write(buf)?;
+            return Ok(0);
+        }
+        if self.finished {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "BCJ filter finished",
+            ));
+        }
+        let mut off = 0;
+        while off < buf.len() {
+            // Copy more unfiltered data into the temporary buffer.
+            let start = self.pos + self.unfiltered;
+            let copy_size = (FILTER_BUF_SIZE - start).min(buf.len() - off);
+            self.filter_buf[start..(start + copy_size)]
+                .copy_from_slice(&buf[off..(off + copy_size)]);
+            off += copy_size;
+            self.unfiltered += copy_size;
+
+            // Filter the data and write out what was filtered.
+            let filtered = self
+                .filter
+                .code(&mut self.filter_buf[self.pos..(self.pos + self.unfiltered)]);
+            assert!(filtered <= self.unfiltered);
+            self.unfiltered -= filtered;
+            self.inner
+                .write_all(&self.filter_buf[self.pos..(self.pos + filtered)])?;
+            self.pos += filtered;
+
+            // If end of filterBuf was reached, move the pending unfiltered
+            // data to the beginning of the buffer.
+            if self.pos + self.unfiltered == FILTER_BUF_SIZE {
+                self.filter_buf.rotate_left(self.pos);
+                self.pos = 0;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        // The pending bytes can't be filtered before more data arrives,
+        // so they stay buffered until the filter is finished.
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,147 @@
+use std::io::Write;
+
+use super::bcj2_decode::{
+    BCJ2_STREAM_CALL, BCJ2_STREAM_JUMP, BCJ2_STREAM_MAIN, BCJ2_STREAM_RC, BIT_MODEL_TOTAL,
+    K_TOP_VALUE, NUM_MODEL_BITS, NUM_MOVE_BITS,
+};
+
+/// Branches are only converted if their relative offset is within this limit,
+/// larger offsets are unlikely to be real `call`/`jmp` instructions.
+const BCJ2_RELAT_LIMIT: u32 = 1 << 26;
+
+#[derive(Debug)]
+pub struct Bcj2Encoder {
+    /// Stream position of the next byte to encode.
+    ip: u32,
+    /// The last byte that was encoded, needed to detect `0F 8x` conditional jumps.
+    prev: u8,
+    probs: [u16; 2 + 256],
+    low: u64,
+    range: u32,
+    cache: u8,
+    cache_size: u64,
+    rc_buf: Vec<u8>,
+}
+
+impl Bcj2Encoder {
+    pub fn new() -> Self {
+        Self {
+            ip: 0,
+            prev: 0,
+            probs: [BIT_MODEL_TOTAL >> 1; 2 + 256],
+            low: 0,
+            range: 0xFFFFFFFF,
+            cache: 0,
+            cache_size: 1,
+            rc_buf: Vec::with_capacity(4096),
+        }
+    }
+
+    /// Encodes `src` into the main, call and jump streams of `outputs` and returns the number of
+    /// bytes consumed. A branch is only decided once its 4 address bytes are available,
+    /// so unless `last` is set, up to 4 bytes at the end of `src` may be left unconsumed.
+    pub fn encode<W: Write>(
+        &mut self,
+        src: &[u8],
+        last: bool,
+        outputs: &mut [W],
+    ) -> std::io::Result<usize> {
+        let mut start = 0;
+        let mut i = 0;
+        while i < src.len() {
+            let b = src[i];
+            if (b & 0xFE) != 0xE8 && !(self.prev == 0x0F && (b & 0xF0) == 0x80) {
+                self.prev = b;
+                i += 1;
+                continue;
+            }
+            if i + 5 > src.len() && !last {
+                break;
+            }
+            outputs[BCJ2_STREAM_MAIN].write_all(&src[start..=i])?;
+            let prob = if b == 0xE8 {
+                2 + self.prev as usize
+            } else if b == 0xE9 {
+                1
+            } else {
+                0
+            };
+            let relat = if i + 5 <= src.len() {
+                u32::from_le_bytes([src[i + 1], src[i + 2], src[i + 3], src[i + 4]])
+            } else {
+                // too close to the end of the stream to be a branch
+                BCJ2_RELAT_LIMIT
+            };
+            if relat.wrapping_add(BCJ2_RELAT_LIMIT) >> 1 < BCJ2_RELAT_LIMIT {
+                self.encode_bit(prob, 1);
+                let ip = self.ip.wrapping_add(i as u32 + 5);
+                let cj = if b == 0xE8 {
+                    BCJ2_STREAM_CALL
+                } else {
+                    BCJ2_STREAM_JUMP
+                };
+                outputs[cj].write_all(&relat.wrapping_add(ip).to_be_bytes())?;
+                self.prev = src[i + 4];
+                i += 5;
+            } else {
+                self.encode_bit(prob, 0);
+                self.prev = b;
+                i += 1;
+            }
+            start = i;
+        }
+        outputs[BCJ2_STREAM_MAIN].write_all(&src[start..i])?;
+        self.ip = self.ip.wrapping_add(i as u32);
+        self.write_rc(outputs)?;
+        Ok(i)
+    }
+
+    /// Flushes the range coder, must be called after the last [Bcj2Encoder::encode].
+    pub fn finish<W: Write>(&mut self, outputs: &mut [W]) -> std::io::Result<()> {
+        for _ in 0..5 {
+            self.shift_low();
+        }
+        self.write_rc(outputs)
+    }
+
+    fn write_rc<W: Write>(&mut self, outputs: &mut [W]) -> std::io::Result<()> {
+        outputs[BCJ2_STREAM_RC].write_all(&self.rc_buf)?;
+        self.rc_buf.clear();
+        Ok(())
+    }
+
+    fn encode_bit(&mut self, prob_index: usize, bit: u32) {
+        let prob = &mut self.probs[prob_index];
+        let bound = (self.range >> NUM_MODEL_BITS) * *prob as u32;
+        if bit == 0 {
+            self.range = bound;
+            *prob += (BIT_MODEL_TOTAL - *prob) >> NUM_MOVE_BITS;
+        } else {
+            self.low += bound as u64;
+            self.range -= bound;
+            *prob -= *prob >> NUM_MOVE_BITS;
+        }
+        while self.range < K_TOP_VALUE {
+            self.range <<= 8;
+            self.shift_low();
+        }
+    }
+
+    fn shift_low(&mut self) {
+        if (self.low as u32) < 0xFF000000 || (self.low >> 32) != 0 {
+            let carry = (self.low >> 32) as u8;
+            let mut temp = self.cache;
+            loop {
+                self.rc_buf.push(temp.wrapping_add(carry));
+                temp = 0xFF;
+                self.cache_size -= 1;
+                if self.cache_size == 0 {
+                    break;
+                }
+            }
+            self.cache = (self.low >> 24) as u8;
+        }
+        self.cache_size += 1;
+        self.low = (self.low & 0x00FFFFFF) << 8;
+    }
+}
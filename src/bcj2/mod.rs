@@ -1,8 +1,16 @@
 mod bcj2_decode;
+#[cfg(feature = "compress")]
+mod bcj2_encode;
 use std::io::Read;
+#[cfg(feature = "compress")]
+use std::io::Write;
 
 use bcj2_decode::*;
+#[cfg(feature = "compress")]
+use bcj2_encode::Bcj2Encoder;
 const BUF_SIZE: usize = 1 << 18;
+/// The memory used by the buffers of [BCJ2Reader].
+pub(crate) const BCJ2_MEM_USAGE_KB: usize = BUF_SIZE * BCJ2_NUM_STREAMS / 1024;
 
 pub struct Bcj2Coder {
     bufs: Vec<u8>,
@@ -161,3 +169,81 @@ impl<R: Read> Read for BCJ2Reader<R> {
         Ok(result_size)
     }
 }
+
+/// Splits the data written to it into the 4 streams of BCJ2, the encoding counterpart of
+/// [BCJ2Reader]. `outputs` are the main, call, jump and range coder streams, in that order.
+///
+/// Writing an empty buffer finishes the encoder and is passed on to all `outputs`.
+#[cfg(feature = "compress")]
+pub struct BCJ2Writer<W> {
+    outputs: Vec<W>,
+    encoder: Bcj2Encoder,
+    pending: Vec<u8>,
+    finished: bool,
+}
+
+#[cfg(feature = "compress")]
+impl<W> BCJ2Writer<W> {
+    pub fn new(outputs: Vec<W>) -> Self {
+        assert_eq!(outputs.len(), BCJ2_NUM_STREAMS);
+        Self {
+            outputs,
+            encoder: Bcj2Encoder::new(),
+            pending: Vec::with_capacity(8),
+            finished: false,
+        }
+    }
+}
+
+#[cfg(feature = "compress")]
+impl<W: Write> BCJ2Writer<W> {
+    fn finish(&mut self) -> std::io::Result<()> {
+        if !self.finished {
+            let pending = std::mem::take(&mut self.pending);
+            self.encoder.encode(&pending, true, &mut self.outputs)?;
+            self.encoder.finish(&mut self.outputs)?;
+            self.finished = true;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "compress")]
+impl<W: Write> Write for BCJ2Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            self.finish()?;
+            for output in self.outputs.iter_mut() {
+                output.write(buf)?;
+            }
+            return Ok(0);
+        }
+        if self.finished {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "BCJ2 finished",
+            ));
+        }
+        let rest = if self.pending.is_empty() {
+            let n = self.encoder.encode(buf, false, &mut self.outputs)?;
+            &buf[n..]
+        } else {
+            // the pending bytes are a branch opcode waiting for its address
+            self.pending.extend_from_slice(buf);
+            let pending = std::mem::take(&mut self.pending);
+            let n = self.encoder.encode(&pending, false, &mut self.outputs)?;
+            self.pending = pending;
+            self.pending.drain(..n);
+            &[]
+        };
+        self.pending.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        for output in self.outputs.iter_mut() {
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
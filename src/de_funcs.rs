@@ -16,6 +16,84 @@ pub fn decompress_file(src_path: impl AsRef<Path>, dest: impl AsRef<Path>) -> Re
     decompress(file, dest)
 }
 
+/// decompress a 7z file, decoding its blocks on up to `threads` threads
+/// # Example
+/// ```no_run
+/// sevenz_rust::decompress_parallel("sample.7z", "sample", 4).expect("complete");
+///
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+#[inline]
+pub fn decompress_parallel(
+    src_path: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    threads: usize,
+) -> Result<(), Error> {
+    decompress_parallel_impl(
+        src_path,
+        dest,
+        threads,
+        Password::empty(),
+        ReaderOptions::default(),
+    )
+}
+
+/// decompress an encrypted 7z file with password, decoding its blocks on up to `threads` threads
+/// # Example
+/// ```no_run
+/// sevenz_rust::decompress_parallel_with_password("sample.7z", "sample", 4, "password".into())
+///     .expect("complete");
+/// ```
+#[cfg(all(feature = "aes256", not(target_arch = "wasm32")))]
+#[inline]
+pub fn decompress_parallel_with_password(
+    src_path: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    threads: usize,
+    password: Password,
+) -> Result<(), Error> {
+    decompress_parallel_impl(src_path, dest, threads, password, ReaderOptions::default())
+}
+
+/// decompress a 7z file on up to `threads` threads with the limits of [ReaderOptions],
+/// stops with [Error::LimitExceeded] once one of them is exceeded
+/// # Example
+/// ```no_run
+/// use sevenz_rust::{Password, ReaderOptions};
+/// let options = ReaderOptions::default().with_max_unpacked_size(1 << 30);
+/// sevenz_rust::decompress_parallel_with_options("sample.7z", "sample", 4, Password::empty(), options)
+///     .expect("complete");
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+#[inline]
+pub fn decompress_parallel_with_options(
+    src_path: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    threads: usize,
+    password: Password,
+    options: ReaderOptions,
+) -> Result<(), Error> {
+    decompress_parallel_impl(src_path, dest, threads, password, options)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn decompress_parallel_impl(
+    src_path: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    threads: usize,
+    password: Password,
+    options: ReaderOptions,
+) -> Result<(), Error> {
+    let seven = SevenZReader::open_with_options(src_path, password, options)?;
+    let dest = PathBuf::from(dest.as_ref());
+    if !dest.exists() {
+        std::fs::create_dir_all(&dest).map_err(Error::io)?;
+    }
+    seven.par_for_each_entries_shared(threads, |entry, reader| {
+        default_entry_extract_fn(entry, reader, &safe_entry_path(&dest, entry.name())?)
+    })
+}
+
 #[inline]
 pub fn decompress_file_with_extract_fn(
     src_path: impl AsRef<Path>,
@@ -40,7 +118,13 @@ pub fn decompress_with_extract_fn<R: Read + Seek>(
     dest: impl AsRef<Path>,
     extract_fn: impl FnMut(&SevenZArchiveEntry, &mut dyn Read, &PathBuf) -> Result<bool, Error>,
 ) -> Result<(), Error> {
-    decompress_impl(src_reader, dest, Password::empty(), extract_fn)
+    decompress_impl(
+        src_reader,
+        dest,
+        Password::empty(),
+        ReaderOptions::default(),
+        extract_fn,
+    )
 }
 
 #[cfg(all(feature = "aes256", not(target_arch = "wasm32")))]
@@ -68,7 +152,13 @@ pub fn decompress_with_password<R: Read + Seek>(
     dest: impl AsRef<Path>,
     password: Password,
 ) -> Result<(), Error> {
-    decompress_impl(src_reader, dest, password, default_entry_extract_fn)
+    decompress_impl(
+        src_reader,
+        dest,
+        password,
+        ReaderOptions::default(),
+        default_entry_extract_fn,
+    )
 }
 
 #[cfg(all(feature = "aes256", not(target_arch = "wasm32")))]
@@ -78,34 +168,216 @@ pub fn decompress_with_extract_fn_and_password<R: Read + Seek>(
     password: Password,
     extract_fn: impl FnMut(&SevenZArchiveEntry, &mut dyn Read, &PathBuf) -> Result<bool, Error>,
 ) -> Result<(), Error> {
-    decompress_impl(src_reader, dest, password, extract_fn)
+    decompress_impl(
+        src_reader,
+        dest,
+        password,
+        ReaderOptions::default(),
+        extract_fn,
+    )
+}
+
+/// decompress a 7z file with the limits of [ReaderOptions], stops with [Error::LimitExceeded]
+/// once one of them is exceeded
+/// # Example
+/// ```no_run
+/// use sevenz_rust::{Password, ReaderOptions};
+/// let options = ReaderOptions::default()
+///     .with_max_unpacked_size(1 << 30)
+///     .with_max_compression_ratio(1000);
+/// sevenz_rust::decompress_file_with_options("sample.7z", "sample", Password::empty(), options)
+///     .expect("complete");
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+#[inline]
+pub fn decompress_file_with_options(
+    src_path: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    password: Password,
+    options: ReaderOptions,
+) -> Result<(), Error> {
+    let file = std::fs::File::open(src_path.as_ref())
+        .map_err(|e| Error::file_open(e, src_path.as_ref().to_string_lossy().to_string()))?;
+    decompress_with_options(file, dest, password, options)
+}
+
+/// decompress a source reader to [dest] path with the limits of [ReaderOptions]
+#[cfg(not(target_arch = "wasm32"))]
+#[inline]
+pub fn decompress_with_options<R: Read + Seek>(
+    src_reader: R,
+    dest: impl AsRef<Path>,
+    password: Password,
+    options: ReaderOptions,
+) -> Result<(), Error> {
+    decompress_impl(
+        src_reader,
+        dest,
+        password,
+        options,
+        default_entry_extract_fn,
+    )
+}
+
+/// decompress a 7z file with the limits of [ReaderOptions], reporting the progress to `progress`
+/// and stopping with [Error::Cancelled] once `cancellation_token` is cancelled
+/// # Example
+/// ```no_run
+/// use sevenz_rust::*;
+/// struct Printer;
+/// impl Progress for Printer {
+///     fn entry_started(&mut self, entry: &SevenZArchiveEntry) {
+///         println!("extracting {}", entry.name());
+///     }
+/// }
+/// let options = ReaderOptions::default();
+/// sevenz_rust::decompress_file_with_progress("sample.7z", "sample", Password::empty(), options, Printer, None)
+///     .expect("complete");
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+#[inline]
+pub fn decompress_file_with_progress(
+    src_path: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    password: Password,
+    options: ReaderOptions,
+    progress: impl Progress + Send + 'static,
+    cancellation_token: Option<CancellationToken>,
+) -> Result<(), Error> {
+    let file = std::fs::File::open(src_path.as_ref())
+        .map_err(|e| Error::file_open(e, src_path.as_ref().to_string_lossy().to_string()))?;
+    decompress_with_progress(file, dest, password, options, progress, cancellation_token)
+}
+
+/// decompress a source reader to [dest] path, reporting the progress to `progress`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn decompress_with_progress<R: Read + Seek>(
+    src_reader: R,
+    dest: impl AsRef<Path>,
+    password: Password,
+    options: ReaderOptions,
+    progress: impl Progress + Send + 'static,
+    cancellation_token: Option<CancellationToken>,
+) -> Result<(), Error> {
+    let mut seven = open_reader(src_reader, password, options)?;
+    seven.set_progress(progress);
+    if let Some(token) = cancellation_token {
+        seven.set_cancellation_token(token);
+    }
+    extract_all(seven, dest, default_entry_extract_fn)
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 fn decompress_impl<R: Read + Seek>(
-    mut src_reader: R,
+    src_reader: R,
     dest: impl AsRef<Path>,
     password: Password,
-    mut extract_fn: impl FnMut(&SevenZArchiveEntry, &mut dyn Read, &PathBuf) -> Result<bool, Error>,
+    options: ReaderOptions,
+    extract_fn: impl FnMut(&SevenZArchiveEntry, &mut dyn Read, &PathBuf) -> Result<bool, Error>,
 ) -> Result<(), Error> {
+    let seven = open_reader(src_reader, password, options)?;
+    extract_all(seven, dest, extract_fn)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn open_reader<R: Read + Seek>(
+    mut src_reader: R,
+    password: Password,
+    options: ReaderOptions,
+) -> Result<SevenZReader<R>, Error> {
     use std::io::SeekFrom;
 
     let pos = src_reader.stream_position().map_err(Error::io)?;
     let len = src_reader.seek(SeekFrom::End(0)).map_err(Error::io)?;
     src_reader.seek(SeekFrom::Start(pos)).map_err(Error::io)?;
-    let mut seven = SevenZReader::new(src_reader, len, password)?;
+    SevenZReader::new_with_options(src_reader, len, password, options)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn extract_all<R: Read + Seek>(
+    mut seven: SevenZReader<R>,
+    dest: impl AsRef<Path>,
+    mut extract_fn: impl FnMut(&SevenZArchiveEntry, &mut dyn Read, &PathBuf) -> Result<bool, Error>,
+) -> Result<(), Error> {
     let dest = PathBuf::from(dest.as_ref());
     if !dest.exists() {
         std::fs::create_dir_all(&dest).map_err(Error::io)?;
     }
     seven.for_each_entries(|entry, reader| {
-        let dest_path = dest.join(entry.name());
+        let dest_path = safe_entry_path(&dest, entry.name())?;
         extract_fn(entry, reader, &dest_path)
     })?;
 
     Ok(())
 }
 
+/// Joins the entry `name` to `dest` without leaving `dest`.
+///
+/// Backslashes are treated as separators, leading `/`, drive prefixes and `.` are dropped
+/// and `..` is resolved. Fails with [Error::UnsafeEntryPath] if the name leads outside of `dest`
+/// or an existing symbolic link on the way points outside of `dest`.
+/// The extraction helpers of this crate use it for every entry.
+/// # Example
+/// ```
+/// use std::path::Path;
+/// let path = sevenz_rust::safe_entry_path(Path::new("out"), "/dir\\file.txt").unwrap();
+/// assert_eq!(path, Path::new("out/dir/file.txt"));
+/// assert!(sevenz_rust::safe_entry_path(Path::new("out"), "../file.txt").is_err());
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+pub fn safe_entry_path(dest: &Path, name: &str) -> Result<PathBuf, Error> {
+    let parts = entry_name_parts(name)?;
+    let mut path = dest.to_path_buf();
+    let mut canonical_dest = None;
+    for part in parts {
+        path.push(part);
+        let is_symlink = std::fs::symlink_metadata(&path)
+            .map(|m| m.file_type().is_symlink())
+            .unwrap_or(false);
+        if !is_symlink {
+            continue;
+        }
+        if canonical_dest.is_none() {
+            canonical_dest = Some(dest.canonicalize().map_err(Error::io)?);
+        }
+        match path.canonicalize() {
+            Ok(target) if target.starts_with(canonical_dest.as_ref().unwrap()) => {}
+            _ => {
+                return Err(Error::unsafe_entry_path(
+                    name,
+                    "symbolic link leads outside of dest",
+                ))
+            }
+        }
+    }
+    Ok(path)
+}
+
+/// Splits the entry `name` into the directories and file name it is extracted to,
+/// as described in [safe_entry_path].
+#[cfg(not(target_arch = "wasm32"))]
+fn entry_name_parts(name: &str) -> Result<Vec<String>, Error> {
+    let normalized = name.replace('\\', "/");
+    let mut parts = Vec::new();
+    for (i, part) in normalized.split('/').enumerate() {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if parts.pop().is_none() {
+                    return Err(Error::unsafe_entry_path(name, "leads outside of dest"));
+                }
+            }
+            // drive prefix like `C:`
+            _ if i == 0 && part.len() == 2 && part.ends_with(':') => {}
+            _ if cfg!(windows) && part.contains(':') => {
+                return Err(Error::unsafe_entry_path(name, "contains ':'"));
+            }
+            _ => parts.push(part.to_string()),
+        }
+    }
+    Ok(parts)
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub fn default_entry_extract_fn(
     entry: &SevenZArchiveEntry,
@@ -118,6 +390,11 @@ pub fn default_entry_extract_fn(
         return Ok(true);
     }
 
+    #[cfg(unix)]
+    if entry.is_symlink() {
+        return extract_symlink(entry, reader, dest).map(|_| true);
+    }
+
     if entry.is_directory() {
         let dir = dest;
         if !dir.exists() {
@@ -148,5 +425,122 @@ pub fn default_entry_extract_fn(
             .unwrap_or_default();
         }
     }
+    restore_permissions(entry, dest)?;
     Ok(true)
 }
+
+/// The maximum length of a symbolic link target.
+const MAX_SYMLINK_TARGET_LEN: u64 = 4096;
+
+/// Creates the symbolic link of `entry` at `path`, replacing a file or link that is already there.
+/// Fails with [Error::UnsafeEntryPath] if the target is absolute or may lead outside of the
+/// directory that the entry names are relative to.
+#[cfg(unix)]
+fn extract_symlink(
+    entry: &SevenZArchiveEntry,
+    reader: &mut dyn Read,
+    path: &Path,
+) -> Result<(), Error> {
+    let mut target = Vec::new();
+    reader
+        .take(MAX_SYMLINK_TARGET_LEN + 1)
+        .read_to_end(&mut target)
+        .map_err(Error::io)?;
+    if target.len() as u64 > MAX_SYMLINK_TARGET_LEN {
+        return Err(Error::unsafe_entry_path(
+            entry.name(),
+            "symbolic link target too long",
+        ));
+    }
+    let target = String::from_utf8(target)
+        .map_err(|_| Error::unsafe_entry_path(entry.name(), "symbolic link target is not UTF-8"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(Error::io)?;
+    }
+    // `path` is the entry name joined to the directory it is extracted to
+    let parts = entry_name_parts(entry.name())?;
+    let root = path.ancestors().nth(parts.len());
+    if parts.is_empty() || root.map_or(true, |root| symlink_leaves_root(root, &parts, &target)) {
+        return Err(Error::unsafe_entry_path(
+            entry.name(),
+            "symbolic link target leads outside of dest",
+        ));
+    }
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if !meta.is_dir() {
+            std::fs::remove_file(path).map_err(Error::io)?;
+        }
+    }
+    std::os::unix::fs::symlink(target, path)
+        .map_err(|e| Error::file_open(e, path.to_string_lossy().to_string()))
+}
+
+/// Returns `true` if `target` of the link extracted to `parts` under `root` is absolute
+/// or may lead outside of `root`.
+///
+/// The directories of the link and the target are walked from `root`. A `..` must not leave
+/// `root`, and must not follow an existing symbolic link or a missing directory, because
+/// where they lead depends on other links, which may be extracted or replaced later.
+#[cfg(unix)]
+fn symlink_leaves_root(root: &Path, parts: &[String], target: &str) -> bool {
+    let target = target.replace('\\', "/");
+    // absolute or with a drive prefix like `C:`
+    if target.starts_with('/') || target.as_bytes().get(1) == Some(&b':') {
+        return true;
+    }
+    let link_dir = parts[..parts.len() - 1].iter().map(String::as_str);
+    let mut path = root.to_path_buf();
+    let mut depth = 0usize;
+    let mut resolved = true;
+    for part in link_dir.chain(target.split('/')) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if !resolved || depth == 0 {
+                    return true;
+                }
+                path.pop();
+                depth -= 1;
+            }
+            _ => {
+                path.push(part);
+                depth += 1;
+                resolved = resolved
+                    && std::fs::symlink_metadata(&path).is_ok_and(|m| !m.file_type().is_symlink());
+            }
+        }
+    }
+    false
+}
+
+/// Applies the Unix permissions of the entry, or its read-only attribute if it has none.
+/// Setuid, setgid and sticky bits are not restored, and directories stay writable
+/// for the owner so that their content can still be extracted.
+#[cfg(unix)]
+fn restore_permissions(entry: &SevenZArchiveEntry, path: &Path) -> Result<(), Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = match entry.unix_permissions() {
+        Some(mode) if entry.is_directory() => mode & 0o777 | 0o700,
+        Some(mode) => mode & 0o777,
+        None if entry.is_read_only() && !entry.is_directory() => {
+            let mode = std::fs::metadata(path)
+                .map_err(Error::io)?
+                .permissions()
+                .mode();
+            mode & !0o222
+        }
+        None => return Ok(()),
+    };
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).map_err(Error::io)
+}
+
+#[cfg(not(unix))]
+fn restore_permissions(entry: &SevenZArchiveEntry, path: &Path) -> Result<(), Error> {
+    if entry.is_read_only() && !entry.is_directory() {
+        let mut permissions = std::fs::metadata(path).map_err(Error::io)?.permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(path, permissions).map_err(Error::io)?;
+    }
+    Ok(())
+}
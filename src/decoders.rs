@@ -3,18 +3,28 @@ use std::io::Read;
 use byteorder::{LittleEndian, ReadBytesExt};
 #[cfg(feature = "bzip2")]
 use bzip2::read::BzDecoder;
+#[cfg(feature = "deflate64")]
+use deflate64::Deflate64Decoder;
+#[cfg(feature = "deflate")]
+use flate2::read::DeflateDecoder;
 
 #[cfg(feature = "aes256")]
 use crate::aes256sha256::Aes256Sha256Decoder;
 use crate::{
     archive::SevenZMethod,
     bcj::SimpleReader,
+    bcj2::BCJ2_MEM_USAGE_KB,
     delta::DeltaReader,
     error::Error,
     folder::Coder,
-    lzma::{lzma2_get_memery_usage, LZMA2Reader, LZMAReader},
+    lzma::{lzma2_get_memery_usage, lzma_get_memery_usage_by_props, LZMA2Reader, LZMAReader},
 };
 
+#[cfg(feature = "zstd")]
+const ZSTD_WINDOW_LOG_MIN: u32 = 10;
+#[cfg(feature = "zstd")]
+const ZSTD_WINDOW_LOG_MAX_DEFAULT: u32 = 27;
+
 pub enum Decoder<R: Read> {
     COPY(R),
     LZMA(LZMAReader<R>),
@@ -25,6 +35,10 @@ pub enum Decoder<R: Read> {
     ZSTD(zstd::Decoder<'static, std::io::BufReader<R>>),
     #[cfg(feature = "bzip2")]
     BZip2(BzDecoder<R>),
+    #[cfg(feature = "deflate")]
+    Deflate(DeflateDecoder<R>),
+    #[cfg(feature = "deflate64")]
+    Deflate64(Deflate64Decoder<std::io::BufReader<R>>),
     #[cfg(feature = "aes256")]
     AES256SHA256(Aes256Sha256Decoder<R>),
 }
@@ -50,6 +64,10 @@ impl<R: Read> Read for Decoder<R> {
             Decoder::Delta(r) => r.read(buf),
             #[cfg(feature = "bzip2")]
             Decoder::BZip2(r) => r.read(buf),
+            #[cfg(feature = "deflate")]
+            Decoder::Deflate(r) => r.read(buf),
+            #[cfg(feature = "deflate64")]
+            Decoder::Deflate64(r) => r.read(buf),
             #[cfg(feature = "aes256")]
             Decoder::AES256SHA256(r) => r.read(buf),
         }
@@ -61,7 +79,7 @@ pub fn add_decoder<I: Read>(
     uncompressed_len: usize,
     coder: &Coder,
     #[allow(unused)] password: &[u8],
-    max_mem_limit_kb: usize,
+    #[allow(unused)] max_mem_limit_kb: usize,
 ) -> Result<Decoder<I>, Error> {
     let method = SevenZMethod::by_id(coder.decompression_method_id());
     let method = if let Some(m) = method {
@@ -76,14 +94,17 @@ pub fn add_decoder<I: Read>(
         SevenZMethod::ID_COPY => Ok(Decoder::COPY(input)),
         #[cfg(feature = "zstd")]
         SevenZMethod::ID_ZSTD => {
-            let zs = zstd::Decoder::new(input).unwrap();
+            let mut zs = zstd::Decoder::new(input).map_err(Error::io)?;
+            // the window size is only known from the frame header, let zstd reject larger windows
+            let window_log_max = (max_mem_limit_kb.saturating_mul(1024) | 1).ilog2();
+            if window_log_max < ZSTD_WINDOW_LOG_MAX_DEFAULT {
+                zs.window_log_max(window_log_max.max(ZSTD_WINDOW_LOG_MIN))
+                    .map_err(Error::io)?;
+            }
             Ok(Decoder::ZSTD(zs))
         }
         SevenZMethod::ID_LZMA => {
             let dict_size = get_lzma_dic_size(coder)?;
-            if coder.properties.is_empty() {
-                return Err(Error::Other("LZMA properties too short".into()));
-            }
             let props = coder.properties[0];
             let lz =
                 LZMAReader::new_with_props(input, uncompressed_len as _, props, dict_size, None)
@@ -92,13 +113,6 @@ pub fn add_decoder<I: Read>(
         }
         SevenZMethod::ID_LZMA2 => {
             let dic_size = get_lzma2_dic_size(coder)?;
-            let mem_size = lzma2_get_memery_usage(dic_size) as usize;
-            if mem_size > max_mem_limit_kb {
-                return Err(Error::MaxMemLimited {
-                    max_kb: max_mem_limit_kb,
-                    actaul_kb: mem_size,
-                });
-            }
             let lz = LZMA2Reader::new(input, dic_size, None);
             Ok(Decoder::LZMA2(lz))
         }
@@ -136,6 +150,16 @@ pub fn add_decoder<I: Read>(
             let de = BzDecoder::new(input);
             Ok(Decoder::BZip2(de))
         }
+        #[cfg(feature = "deflate")]
+        SevenZMethod::ID_DEFLATE => {
+            let de = DeflateDecoder::new(input);
+            Ok(Decoder::Deflate(de))
+        }
+        #[cfg(feature = "deflate64")]
+        SevenZMethod::ID_DEFLATE64 => {
+            let de = Deflate64Decoder::new(input);
+            Ok(Decoder::Deflate64(de))
+        }
         #[cfg(feature = "aes256")]
         SevenZMethod::ID_AES256SHA256 => {
             if password.is_empty() {
@@ -150,6 +174,21 @@ pub fn add_decoder<I: Read>(
     }
 }
 
+/// Returns the memory in KiB the decoder of `coder` allocates, as far as it is known from
+/// the coder properties.
+pub fn get_mem_usage_kb(coder: &Coder) -> Result<usize, Error> {
+    let mem_usage = match coder.decompression_method_id() {
+        SevenZMethod::ID_LZMA => {
+            let dict_size = get_lzma_dic_size(coder)?;
+            lzma_get_memery_usage_by_props(dict_size, coder.properties[0]).map_err(Error::io)?
+        }
+        SevenZMethod::ID_LZMA2 => lzma2_get_memery_usage(get_lzma2_dic_size(coder)?),
+        SevenZMethod::ID_BCJ2 => BCJ2_MEM_USAGE_KB as u32,
+        _ => 0,
+    };
+    Ok(mem_usage as usize)
+}
+
 #[inline]
 fn get_lzma2_dic_size(coder: &Coder) -> Result<u32, Error> {
     if coder.properties.is_empty() {
@@ -171,6 +210,9 @@ fn get_lzma2_dic_size(coder: &Coder) -> Result<u32, Error> {
 
 #[inline]
 fn get_lzma_dic_size(coder: &Coder) -> Result<u32, Error> {
+    if coder.properties.len() < 5 {
+        return Err(Error::other("LZMA properties too short"));
+    }
     let mut props = &coder.properties[1..5];
     props.read_u32::<LittleEndian>().map_err(Error::io)
 }
@@ -1,7 +1,9 @@
 use std::io::Read;
+#[cfg(feature = "compress")]
+use std::io::Write;
 
-const MAX_DISTANCE: usize = 256;
-const _MIN_DISTANCE: usize = 1;
+pub(crate) const MAX_DISTANCE: usize = 256;
+pub(crate) const MIN_DISTANCE: usize = 1;
 const DIS_MASK: usize = MAX_DISTANCE - 1;
 struct Delta {
     distance: usize,
@@ -27,6 +29,17 @@ impl Delta {
             self.pos = self.pos.wrapping_sub(1);
         }
     }
+
+    #[cfg(feature = "compress")]
+    pub fn encode(&mut self, input: &[u8], out: &mut [u8]) {
+        for (i, item) in input.iter().enumerate() {
+            let pos = self.pos as usize;
+            let h = self.history[(self.distance.wrapping_add(pos)) & DIS_MASK];
+            self.history[pos & DIS_MASK] = *item;
+            out[i] = item.wrapping_sub(h);
+            self.pos = self.pos.wrapping_sub(1);
+        }
+    }
 }
 
 pub struct DeltaReader<R> {
@@ -53,3 +66,44 @@ impl<R: Read> Read for DeltaReader<R> {
         Ok(n)
     }
 }
+
+#[cfg(feature = "compress")]
+const ENCODE_BUF_SIZE: usize = 4096;
+
+#[cfg(feature = "compress")]
+pub struct DeltaWriter<W> {
+    inner: W,
+    delta: Delta,
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "compress")]
+impl<W> DeltaWriter<W> {
+    pub fn new(inner: W, distance: usize) -> Self {
+        Self {
+            inner,
+            delta: Delta::new(distance),
+            buf: vec![0; ENCODE_BUF_SIZE],
+        }
+    }
+}
+
+#[cfg(feature = "compress")]
+impl<W: Write> Write for DeltaWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            // pass the finishing empty write on to the inner encoder
+            return self.inner.write(buf);
+        }
+        for chunk in buf.chunks(ENCODE_BUF_SIZE) {
+            let out = &mut self.buf[..chunk.len()];
+            self.delta.encode(chunk, out);
+            self.inner.write_all(out)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
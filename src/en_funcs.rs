@@ -17,7 +17,7 @@ pub fn compress<W: Write + Seek>(src: impl AsRef<Path>, dest: W) -> Result<W, Er
     } else {
         src.as_ref().parent().unwrap_or(src.as_ref())
     };
-    compress_path(src.as_ref(), parent, &mut z)?;
+    compress_path(src.as_ref(), parent, &mut z, &mut Vec::new())?;
     z.finish().map_err(Error::io)
 }
 
@@ -39,7 +39,7 @@ pub fn compress_encypted<W: Write + Seek>(
     } else {
         src.as_ref().parent().unwrap_or(src.as_ref())
     };
-    compress_path(src.as_ref(), parent, &mut z)?;
+    compress_path(src.as_ref(), parent, &mut z, &mut Vec::new())?;
     z.finish().map_err(Error::io)
 }
 
@@ -80,10 +80,12 @@ pub fn compress_to_path_encrypted(
     Ok(())
 }
 
+/// `ancestors` are the canonical paths of the directories that contain `src`.
 fn compress_path<W: Write + Seek, P: AsRef<Path>>(
     src: P,
     root: &Path,
     z: &mut SevenZWriter<W>,
+    ancestors: &mut Vec<PathBuf>,
 ) -> Result<(), Error> {
     let entry_name = src
         .as_ref()
@@ -91,20 +93,32 @@ fn compress_path<W: Write + Seek, P: AsRef<Path>>(
         .map_err(|e| Error::other(e.to_string()))?
         .to_string_lossy()
         .to_string();
-    let entry = SevenZArchiveEntry::from_path(src.as_ref(), entry_name);
     let path = src.as_ref();
+    if !z.follow_symlinks && path != root && is_symlink(path) {
+        let entry = SevenZArchiveEntry::from_symlink_path(path, entry_name);
+        z.push_archive_entry(entry, Some(LazyFileReader::new_symlink(path.to_path_buf())))?;
+        return Ok(());
+    }
+    let entry = SevenZArchiveEntry::from_path(src.as_ref(), entry_name);
     if path.is_dir() {
+        let canonical = path.canonicalize().map_err(Error::io)?;
+        // a followed link to a directory that contains it would be walked forever
+        if ancestors.contains(&canonical) {
+            return Ok(());
+        }
         z.push_archive_entry::<&[u8]>(entry, None)?;
+        ancestors.push(canonical);
         for dir in path
             .read_dir()
             .map_err(|e| Error::io_msg(e, "error read dir"))?
         {
             let dir = dir.map_err(Error::io)?;
             let ftype = dir.file_type().map_err(Error::io)?;
-            if ftype.is_dir() || ftype.is_file() {
-                compress_path(dir.path(), root, z)?;
+            if ftype.is_dir() || ftype.is_file() || is_stored_symlink(&dir, z.follow_symlinks) {
+                compress_path(dir.path(), root, z, ancestors)?;
             }
         }
+        ancestors.pop();
     } else {
         z.push_archive_entry(
             entry,
@@ -134,7 +148,8 @@ impl<W: Write + Seek> SevenZWriter<W> {
 
     /// [Non-solid compression]
     /// compress all files in [path].
-    /// With one file per block.
+    /// With one file per block, compressed concurrently if
+    /// [set_num_threads](SevenZWriter::set_num_threads) is more than 1.
     pub fn push_source_path_non_solid(
         &mut self,
         path: impl AsRef<Path>,
@@ -145,23 +160,64 @@ impl<W: Write + Seek> SevenZWriter<W> {
     }
 }
 
+fn is_symlink(path: &Path) -> bool {
+    path.symlink_metadata()
+        .is_ok_and(|meta| meta.file_type().is_symlink())
+}
+
+/// Returns `true` for a symbolic link that is stored as a link, or is followed to an existing file.
+fn is_stored_symlink(dir: &std::fs::DirEntry, follow_symlinks: bool) -> bool {
+    dir.file_type().is_ok_and(|ftype| ftype.is_symlink())
+        && (!follow_symlinks || dir.path().exists())
+}
+
+/// Returns the entry for `path` and the reader of its content,
+/// which is the link target for a symbolic link that is not followed.
+fn entry_with_reader(
+    path: PathBuf,
+    name: String,
+    follow_symlinks: bool,
+) -> (SevenZArchiveEntry, LazyFileReader) {
+    if !follow_symlinks && is_symlink(&path) {
+        let entry = SevenZArchiveEntry::from_symlink_path(&path, name);
+        (entry, LazyFileReader::new_symlink(path))
+    } else {
+        let entry = SevenZArchiveEntry::from_path(&path, name);
+        (entry, LazyFileReader::new(path))
+    }
+}
+
+/// `ancestors` are the canonical paths of the directories that contain `src`.
 fn collect_file_paths(
     src: impl AsRef<Path>,
     paths: &mut Vec<PathBuf>,
     filter: &dyn Fn(&Path) -> bool,
+    follow_symlinks: bool,
+    ancestors: &mut Vec<PathBuf>,
 ) -> std::io::Result<()> {
     let path = src.as_ref();
     if !filter(path) {
         return Ok(());
     }
     if path.is_dir() {
+        let canonical = path.canonicalize()?;
+        // a followed link to a directory that contains it would be walked forever
+        if ancestors.contains(&canonical) {
+            return Ok(());
+        }
+        ancestors.push(canonical);
         for dir in path.read_dir()? {
             let dir = dir?;
             let ftype = dir.file_type()?;
-            if ftype.is_file() || ftype.is_dir() {
-                collect_file_paths(dir.path(), paths, filter)?;
+            if ftype.is_symlink() && !follow_symlinks {
+                if filter(&dir.path()) {
+                    paths.push(dir.path());
+                }
+            } else if ftype.is_file() || ftype.is_dir() || is_stored_symlink(&dir, true) {
+                collect_file_paths(dir.path(), paths, filter, follow_symlinks, ancestors)?;
             }
         }
+        ancestors.pop();
     } else {
         paths.push(path.to_path_buf())
     }
@@ -177,30 +233,39 @@ fn encode_path<W: Write + Seek>(
 ) -> Result<(), crate::Error> {
     let mut entries = Vec::new();
     let mut paths = Vec::new();
-    collect_file_paths(&src, &mut paths, &filter).map_err(|e| {
-        crate::Error::io_msg(
-            e,
-            format!("Failed to collect entries from path:{:?}", src.as_ref()),
-        )
-    })?;
+    let follow_symlinks = zip.follow_symlinks;
+    collect_file_paths(&src, &mut paths, &filter, follow_symlinks, &mut Vec::new()).map_err(
+        |e| {
+            crate::Error::io_msg(
+                e,
+                format!("Failed to collect entries from path:{:?}", src.as_ref()),
+            )
+        },
+    )?;
     if !solid {
-        for ele in paths.into_iter() {
-            let name = ele
-                .strip_prefix(&src)
-                .unwrap()
-                .to_string_lossy()
-                .to_string();
-            zip.push_archive_entry(
-                SevenZArchiveEntry::from_path(ele.as_path(), name),
-                Some(File::open(ele.as_path()).map_err(crate::Error::io)?),
-            )?;
-        }
+        let entries = paths
+            .into_iter()
+            .map(|ele| {
+                let name = ele
+                    .strip_prefix(&src)
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                let (entry, reader) = entry_with_reader(ele, name, follow_symlinks);
+                (entry, Some(reader))
+            })
+            .collect();
+        zip.push_archive_entries_non_solid(entries)?;
         return Ok(());
     }
     let mut files = Vec::new();
     let mut file_size = 0;
     for ele in paths.into_iter() {
-        let size = ele.metadata()?.len();
+        let size = if follow_symlinks {
+            ele.metadata()?.len()
+        } else {
+            ele.symlink_metadata()?.len()
+        };
         let name = ele
             .strip_prefix(&src)
             .unwrap()
@@ -220,8 +285,9 @@ fn encode_path<W: Write + Seek>(
             file_size = 0;
         }
         file_size += size;
-        entries.push(SevenZArchiveEntry::from_path(ele.as_path(), name));
-        files.push(LazyFileReader::new(ele).into());
+        let (entry, reader) = entry_with_reader(ele, name, follow_symlinks);
+        entries.push(entry);
+        files.push(reader.into());
     }
     if !entries.is_empty() {
         zip.push_archive_entries(entries, SeqReader::new(files))?;
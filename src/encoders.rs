@@ -1,18 +1,30 @@
 use std::io::Write;
 
+#[cfg(feature = "bzip2")]
+use bzip2::write::BzEncoder;
+
 #[cfg(feature = "aes256")]
 use crate::aes256sha256::Aes256Sha256Encoder;
 use crate::{
     archive::{SevenZMethod, SevenZMethodConfiguration},
+    bcj::SimpleWriter,
+    delta::{DeltaWriter, MIN_DISTANCE},
     lzma::CountingWriter,
-    lzma::{LZMA2Options, LZMA2Writer, LZMAWriter},
+    lzma::{LZMA2MtWriter, LZMA2Options, LZMA2Writer, LZMAWriter},
     method_options::MethodOptions,
     Error,
 };
 
 pub enum Encoder<W: Write> {
+    BCJ(SimpleWriter<CountingWriter<W>>),
+    Delta(DeltaWriter<CountingWriter<W>>),
+    #[cfg(feature = "zstd")]
+    ZSTD(zstd::Encoder<'static, CountingWriter<W>>),
+    #[cfg(feature = "bzip2")]
+    BZip2(BzEncoder<CountingWriter<W>>),
     LZMA(LZMAWriter<W>),
     LZMA2(LZMA2Writer<W>),
+    LZMA2Mt(LZMA2MtWriter<W>),
     #[cfg(feature = "aes256")]
     AES(Aes256Sha256Encoder<W>),
 }
@@ -20,8 +32,27 @@ pub enum Encoder<W: Write> {
 impl<W: Write> Write for Encoder<W> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         match self {
+            Encoder::BCJ(w) => w.write(buf),
+            Encoder::Delta(w) => w.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::ZSTD(w) => {
+                if buf.is_empty() {
+                    w.do_finish()?;
+                    return w.get_mut().write(buf);
+                }
+                w.write(buf)
+            }
+            #[cfg(feature = "bzip2")]
+            Encoder::BZip2(w) => {
+                if buf.is_empty() {
+                    w.try_finish()?;
+                    return w.get_mut().write(buf);
+                }
+                w.write(buf)
+            }
             Encoder::LZMA(w) => w.write(buf),
             Encoder::LZMA2(w) => w.write(buf),
+            Encoder::LZMA2Mt(w) => w.write(buf),
             #[cfg(feature = "aes256")]
             Encoder::AES(w) => w.write(buf),
         }
@@ -29,8 +60,15 @@ impl<W: Write> Write for Encoder<W> {
 
     fn flush(&mut self) -> std::io::Result<()> {
         match self {
+            Encoder::BCJ(w) => w.flush(),
+            Encoder::Delta(w) => w.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::ZSTD(w) => w.flush(),
+            #[cfg(feature = "bzip2")]
+            Encoder::BZip2(w) => w.flush(),
             Encoder::LZMA(w) => w.flush(),
             Encoder::LZMA2(w) => w.flush(),
+            Encoder::LZMA2Mt(w) => w.flush(),
             #[cfg(feature = "aes256")]
             Encoder::AES(w) => w.flush(),
         }
@@ -51,12 +89,36 @@ pub fn add_encoder<W: Write>(
             Ok(Encoder::LZMA(lz))
         }
         SevenZMethod::ID_LZMA2 => {
+            if let Some(MethodOptions::LZMA2Mt(options)) = method_config.options.as_ref() {
+                let lz = LZMA2MtWriter::new(input, options);
+                return Ok(Encoder::LZMA2Mt(lz));
+            }
             let mut def_opts = LZMA2Options::default();
             let options = get_lzma2_options(method_config.options.as_ref(), &mut def_opts);
-
             let lz = LZMA2Writer::new(input, options);
             Ok(Encoder::LZMA2(lz))
         }
+        SevenZMethod::ID_BCJ_X86 => Ok(Encoder::BCJ(SimpleWriter::new_x86(input))),
+        SevenZMethod::ID_BCJ_ARM => Ok(Encoder::BCJ(SimpleWriter::new_arm(input))),
+        SevenZMethod::ID_BCJ_ARM_THUMB => Ok(Encoder::BCJ(SimpleWriter::new_arm_thumb(input))),
+        SevenZMethod::ID_BCJ_PPC => Ok(Encoder::BCJ(SimpleWriter::new_ppc(input))),
+        SevenZMethod::ID_BCJ_SPARC => Ok(Encoder::BCJ(SimpleWriter::new_sparc(input))),
+        #[cfg(feature = "zstd")]
+        SevenZMethod::ID_ZSTD => {
+            let level = get_level(method_config.options.as_ref(), ZSTD_LEVEL_DEFAULT).clamp(1, 22);
+            let zs = zstd::Encoder::new(input, level as i32).map_err(Error::io)?;
+            Ok(Encoder::ZSTD(zs))
+        }
+        #[cfg(feature = "bzip2")]
+        SevenZMethod::ID_BZIP2 => {
+            let level = get_level(method_config.options.as_ref(), BZIP2_LEVEL_DEFAULT);
+            let bz = BzEncoder::new(input, bzip2::Compression::new(level.clamp(1, 9)));
+            Ok(Encoder::BZip2(bz))
+        }
+        SevenZMethod::ID_DELTA => {
+            let distance = get_delta_distance(method_config.options.as_ref())?;
+            Ok(Encoder::Delta(DeltaWriter::new(input, distance)))
+        }
         #[cfg(feature = "aes256")]
         SevenZMethod::ID_AES256SHA256 => {
             let options = match method_config.options.as_ref() {
@@ -96,6 +158,23 @@ pub(crate) fn get_options_as_properties<'a>(
             out[1..5].copy_from_slice(dict_size.to_le_bytes().as_ref());
             &out[0..5]
         }
+        #[cfg(feature = "zstd")]
+        SevenZMethod::ID_ZSTD => {
+            // 7-Zip-zstd: version of the zstd library, the level and 2 reserved bytes
+            let version = zstd::zstd_safe::version_number();
+            out[0] = (version / 10000) as u8;
+            out[1] = (version / 100 % 100) as u8;
+            out[2] = get_level(options, ZSTD_LEVEL_DEFAULT).clamp(1, 22) as u8;
+            out[3] = 0;
+            out[4] = 0;
+            &out[0..5]
+        }
+        SevenZMethod::ID_DELTA => {
+            // the distance was checked when the encoder was added
+            let distance = get_delta_distance(options).unwrap_or(MIN_DISTANCE);
+            out[0] = (distance - 1) as u8;
+            &out[0..1]
+        }
         #[cfg(feature = "aes256")]
         SevenZMethod::ID_AES256SHA256 => {
             let options = match options.as_ref() {
@@ -109,6 +188,26 @@ pub(crate) fn get_options_as_properties<'a>(
     }
 }
 
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL_DEFAULT: u32 = 3;
+#[cfg(feature = "bzip2")]
+const BZIP2_LEVEL_DEFAULT: u32 = 6;
+
+#[cfg(any(feature = "zstd", feature = "bzip2"))]
+#[inline]
+fn get_level(options: Option<&MethodOptions>, default: u32) -> u32 {
+    match options {
+        Some(MethodOptions::Num(level)) => *level,
+        _ => default,
+    }
+}
+
+#[inline]
+fn get_delta_distance(options: Option<&MethodOptions>) -> Result<usize, Error> {
+    let distance = options.map_or(Ok(1), |o| o.get_delta_distance())?;
+    Ok(distance as usize)
+}
+
 #[inline]
 pub(crate) fn get_lzma2_options<'a>(
     options: Option<&'a MethodOptions>,
@@ -116,6 +215,7 @@ pub(crate) fn get_lzma2_options<'a>(
 ) -> &'a LZMA2Options {
     let options = match options.as_ref() {
         Some(MethodOptions::LZMA2(opts)) => opts,
+        Some(MethodOptions::LZMA2Mt(opts)) => &opts.lzma2,
         Some(MethodOptions::Num(n)) => {
             def_opt.dict_size = *n;
             def_opt
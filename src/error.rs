@@ -1,5 +1,6 @@
 use std::{borrow::Cow, fmt::Display};
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     BadSignature([u8; 6]),
     UnsupportedVersion { major: u8, minor: u8 },
@@ -20,6 +21,21 @@ pub enum Error {
     PasswordRequired,
     Unsupported(Cow<'static, str>),
     MaybeBadPassword(std::io::Error),
+    // a limit of ReaderOptions was exceeded while decoding
+    LimitExceeded(ExtractLimit),
+    // the entry name and why it would be extracted outside of the destination directory
+    UnsafeEntryPath(String, Cow<'static, str>),
+    // the CancellationToken was cancelled
+    Cancelled,
+}
+
+/// The limit of [ReaderOptions](crate::ReaderOptions) that was exceeded, with its configured value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtractLimit {
+    UnpackedSize(u64),
+    EntryCount(usize),
+    EntrySize(u64),
+    CompressionRatio(u64),
 }
 
 impl From<std::io::Error> for Error {
@@ -55,6 +71,14 @@ impl Error {
         }
     }
 
+    #[inline]
+    pub(crate) fn unsafe_entry_path(
+        name: impl Into<String>,
+        reason: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        Self::UnsafeEntryPath(name.into(), reason.into())
+    }
+
     #[inline]
     pub(crate) fn file_open(e: std::io::Error, filename: impl Into<Cow<'static, str>>) -> Self {
         Self::Io(e, filename.into())
@@ -6,9 +6,19 @@
 extern crate wasm_bindgen;
 #[cfg(feature = "aes256")]
 mod aes256sha256;
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+mod async_bridge;
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+mod async_reader;
+#[cfg(all(feature = "async", feature = "compress", not(target_arch = "wasm32")))]
+mod async_writer;
 mod bcj2;
 #[cfg(feature = "aes256")]
 pub use aes256sha256::*;
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+pub use async_reader::{AsyncEntries, AsyncEntryReader, AsyncSevenZReader};
+#[cfg(all(feature = "async", feature = "compress", not(target_arch = "wasm32")))]
+pub use async_writer::AsyncSevenZWriter;
 #[cfg(target_arch = "wasm32")]
 mod wasm;
 extern crate filetime_creation as ft;
@@ -27,7 +37,13 @@ pub(crate) mod folder;
 mod method_options;
 pub use method_options::*;
 mod password;
+mod progress;
+mod read_at;
 mod reader;
+#[cfg(not(target_arch = "wasm32"))]
+mod stream_reader;
+#[cfg(not(target_arch = "wasm32"))]
+mod volume;
 #[cfg(feature = "compress")]
 mod writer;
 pub use archive::*;
@@ -35,11 +51,20 @@ pub use archive::*;
 pub use de_funcs::*;
 #[cfg(feature = "compress")]
 pub use en_funcs::*;
-pub use error::Error;
+pub use error::{Error, ExtractLimit};
 pub use lzma_rust as lzma;
 pub use nt_time;
 pub use password::Password;
+pub use progress::{CancellationToken, Progress};
+pub use read_at::ReadAt;
 pub use reader::BlockDecoder;
+pub use reader::ReaderOptions;
 pub use reader::SevenZReader;
+pub use reader::{Entries, EntryReader};
+pub use reader::{EntryTestResult, TestReport, TestStatus};
+#[cfg(not(target_arch = "wasm32"))]
+pub use stream_reader::{SpooledInput, StreamInput};
+#[cfg(not(target_arch = "wasm32"))]
+pub use volume::*;
 #[cfg(feature = "compress")]
 pub use writer::*;
@@ -1,15 +1,19 @@
 #[cfg(feature = "compress")]
-use lzma_rust::LZMA2Options;
+use lzma_rust::{LZMA2MtOptions, LZMA2Options};
 
 #[cfg(feature = "aes256")]
 use crate::aes256sha256::AesEncoderOptions;
 use std::fmt::Debug;
 
+use crate::delta::{MAX_DISTANCE, MIN_DISTANCE};
+
 #[derive(Debug, Clone)]
 pub enum MethodOptions {
     Num(u32),
     #[cfg(feature = "compress")]
     LZMA2(crate::lzma::LZMA2Options),
+    #[cfg(feature = "compress")]
+    LZMA2Mt(crate::lzma::LZMA2MtOptions),
     #[cfg(feature = "aes256")]
     Aes(AesEncoderOptions),
 }
@@ -34,6 +38,13 @@ impl From<LZMA2Options> for crate::SevenZMethodConfiguration {
     }
 }
 
+#[cfg(feature = "compress")]
+impl From<LZMA2MtOptions> for crate::SevenZMethodConfiguration {
+    fn from(value: LZMA2MtOptions) -> Self {
+        Self::new(crate::SevenZMethod::LZMA2).with_options(MethodOptions::LZMA2Mt(value))
+    }
+}
+
 impl From<u32> for MethodOptions {
     fn from(n: u32) -> Self {
         Self::Num(n)
@@ -47,14 +58,41 @@ impl From<crate::lzma::LZMA2Options> for MethodOptions {
     }
 }
 
+#[cfg(feature = "compress")]
+impl From<crate::lzma::LZMA2MtOptions> for MethodOptions {
+    fn from(o: crate::lzma::LZMA2MtOptions) -> Self {
+        Self::LZMA2Mt(o)
+    }
+}
+
 impl MethodOptions {
     pub fn get_lzma2_dict_size(&self) -> u32 {
         match self {
             MethodOptions::Num(n) => *n,
             #[cfg(feature = "compress")]
             MethodOptions::LZMA2(o) => o.dict_size,
+            #[cfg(feature = "compress")]
+            MethodOptions::LZMA2Mt(o) => o.lzma2.dict_size,
             #[allow(unused)]
             _ => 0,
         }
     }
+
+    /// The distance of the delta filter, in bytes. Only [MethodOptions::Num] carries it,
+    /// fails for other options or a distance outside of 1..=256.
+    pub fn get_delta_distance(&self) -> Result<u32, crate::Error> {
+        match self {
+            MethodOptions::Num(n) if (MIN_DISTANCE..=MAX_DISTANCE).contains(&(*n as usize)) => {
+                Ok(*n)
+            }
+            MethodOptions::Num(n) => Err(crate::Error::other(format!(
+                "Delta distance must be in {}..={}, got {}",
+                MIN_DISTANCE, MAX_DISTANCE, n
+            ))),
+            #[allow(unused)]
+            _ => Err(crate::Error::other(
+                "Delta filter options must be a distance",
+            )),
+        }
+    }
 }
@@ -0,0 +1,176 @@
+use std::{
+    cell::Cell,
+    io::{ErrorKind, Read, Seek, SeekFrom},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+#[cfg(feature = "compress")]
+use std::io::Write;
+
+use crate::{Error, SevenZArchiveEntry};
+
+/// Receives the progress of compression and extraction,
+/// see [SevenZWriter::set_progress](crate::SevenZWriter::set_progress),
+/// [SevenZReader::set_progress](crate::SevenZReader::set_progress)
+/// and [BlockDecoder::with_progress](crate::BlockDecoder::with_progress).
+///
+/// All methods do nothing by default.
+pub trait Progress {
+    /// Called before the content of `entry` is compressed or extracted.
+    fn entry_started(&mut self, _entry: &SevenZArchiveEntry) {}
+
+    /// Called after `entry` was compressed or extracted.
+    fn entry_finished(&mut self, _entry: &SevenZArchiveEntry) {}
+
+    /// Called with the number of `unpacked` bytes read from or passed to the entries
+    /// and of `packed` bytes written to or read from the archive since the last call.
+    /// Add them up for the totals.
+    fn bytes_processed(&mut self, _unpacked: u64, _packed: u64) {}
+}
+
+/// Stops compression or extraction with [Error::Cancelled] once [cancel](Self::cancel)
+/// is called on any of its clones, e.g. from a GUI thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Returns whether `token` is set and cancelled.
+pub(crate) fn is_cancelled(token: Option<&CancellationToken>) -> bool {
+    token.is_some_and(CancellationToken::is_cancelled)
+}
+
+/// Counts the bytes read from `inner` into `count`.
+pub(crate) struct PackedCountingReader<'a, R> {
+    inner: &'a mut R,
+    count: &'a Cell<u64>,
+}
+
+impl<'a, R> PackedCountingReader<'a, R> {
+    pub fn new(inner: &'a mut R, count: &'a Cell<u64>) -> Self {
+        Self { inner, count }
+    }
+}
+
+impl<'a, R: Read> Read for PackedCountingReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.count.set(self.count.get() + size as u64);
+        Ok(size)
+    }
+}
+
+impl<'a, R: Seek> Seek for PackedCountingReader<'a, R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+/// Counts the bytes written to `inner` into `count`.
+#[cfg(feature = "compress")]
+pub(crate) struct PackedCountingWriter<'a, W> {
+    inner: W,
+    count: &'a Cell<u64>,
+}
+
+#[cfg(feature = "compress")]
+impl<'a, W> PackedCountingWriter<'a, W> {
+    pub fn new(inner: W, count: &'a Cell<u64>) -> Self {
+        Self { inner, count }
+    }
+}
+
+#[cfg(feature = "compress")]
+impl<'a, W: Write> Write for PackedCountingWriter<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let size = self.inner.write(buf)?;
+        self.count.set(self.count.get() + size as u64);
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reports the bytes read from `inner`, with the packed bytes counted in `packed` from 0,
+/// and fails with [Error::Cancelled] once the token is cancelled.
+pub(crate) struct ProgressReader<'c, 'p, R> {
+    pub inner: R,
+    progress: Option<&'p mut dyn Progress>,
+    token: Option<&'p CancellationToken>,
+    packed: &'c Cell<u64>,
+    reported_packed: u64,
+}
+
+impl<'c, 'p, R> ProgressReader<'c, 'p, R> {
+    pub fn new(
+        inner: R,
+        progress: Option<&'p mut dyn Progress>,
+        token: Option<&'p CancellationToken>,
+        packed: &'c Cell<u64>,
+    ) -> Self {
+        Self {
+            inner,
+            progress,
+            token,
+            packed,
+            reported_packed: 0,
+        }
+    }
+
+    /// Reports `unpacked` bytes with the packed bytes counted since the last report.
+    pub fn report(&mut self, unpacked: u64) {
+        let packed = self.packed.get() - self.reported_packed;
+        self.reported_packed = self.packed.get();
+        if let Some(progress) = &mut self.progress {
+            if unpacked > 0 || packed > 0 {
+                progress.bytes_processed(unpacked, packed);
+            }
+        }
+    }
+
+    pub fn entry_started(&mut self, entry: &SevenZArchiveEntry) {
+        if let Some(progress) = &mut self.progress {
+            progress.entry_started(entry);
+        }
+    }
+
+    pub fn entry_finished(&mut self, entry: &SevenZArchiveEntry) {
+        if let Some(progress) = &mut self.progress {
+            progress.entry_finished(entry);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        is_cancelled(self.token)
+    }
+}
+
+impl<'c, 'p, R: Read> Read for ProgressReader<'c, 'p, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.is_cancelled() {
+            return Err(std::io::Error::new(ErrorKind::Other, Error::Cancelled));
+        }
+        let size = self.inner.read(buf)?;
+        self.report(size as u64);
+        Ok(size)
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    cell::RefCell,
+    io::{ErrorKind, Read, Seek, SeekFrom},
+    sync::Arc,
+};
+
+/// Reads from a source at absolute positions without a shared cursor,
+/// like `pread` on a file or slicing a buffer.
+///
+/// Readers created from one `ReadAt` source don't affect each other, so several
+/// pack streams or blocks can be decoded from the same source, also from multiple threads
+/// if the source is [Sync].
+pub trait ReadAt {
+    /// Reads bytes starting at `pos` into `buf`, returns the number of bytes read.
+    /// Returns 0 if `pos` is at or past the end of the source.
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize>;
+}
+
+impl ReadAt for [u8] {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let start = pos.min(self.len() as u64) as usize;
+        let len = buf.len().min(self.len() - start);
+        buf[..len].copy_from_slice(&self[start..start + len]);
+        Ok(len)
+    }
+}
+
+impl ReadAt for Vec<u8> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.as_slice().read_at(pos, buf)
+    }
+}
+
+impl<T: AsRef<[u8]>> ReadAt for std::io::Cursor<T> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.get_ref().as_ref().read_at(pos, buf)
+    }
+}
+
+#[cfg(unix)]
+impl ReadAt for std::fs::File {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        std::os::unix::fs::FileExt::read_at(self, buf, pos)
+    }
+}
+
+#[cfg(windows)]
+impl ReadAt for std::fs::File {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        std::os::windows::fs::FileExt::seek_read(self, buf, pos)
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for &T {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        (**self).read_at(pos, buf)
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for Arc<T> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        (**self).read_at(pos, buf)
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for std::rc::Rc<T> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        (**self).read_at(pos, buf)
+    }
+}
+
+/// Adapts a [Read] + [Seek] source by seeking before every read.
+pub(crate) struct SeekReadAt<R>(RefCell<R>);
+
+impl<R: Read + Seek> SeekReadAt<R> {
+    pub fn new(inner: R) -> Self {
+        Self(RefCell::new(inner))
+    }
+}
+
+impl<R: Read + Seek> ReadAt for SeekReadAt<R> {
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut inner = self.0.borrow_mut();
+        inner.seek(SeekFrom::Start(pos))?;
+        inner.read(buf)
+    }
+}
+
+/// A [Read] + [Seek] view of the range `start..end` of a [ReadAt] source
+/// with its own position.
+#[derive(Debug, Clone)]
+pub(crate) struct ReadAtReader<S> {
+    source: S,
+    start: u64,
+    end: u64,
+    pos: u64,
+}
+
+impl<S: ReadAt> ReadAtReader<S> {
+    pub fn new(source: S, start: u64, end: u64) -> Self {
+        Self {
+            source,
+            start,
+            end,
+            pos: start,
+        }
+    }
+}
+
+impl<S: ReadAt> Read for ReadAtReader<S> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos >= self.end {
+            return Ok(0);
+        }
+        let len = (buf.len() as u64).min(self.end - self.pos) as usize;
+        let size = self.source.read_at(self.pos, &mut buf[..len])?;
+        self.pos += size as u64;
+        Ok(size)
+    }
+}
+
+impl<S: ReadAt> Seek for ReadAtReader<S> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(pos) => self.start.checked_add(pos),
+            SeekFrom::End(pos) => self.end.checked_add_signed(pos),
+            SeekFrom::Current(pos) => self.pos.checked_add_signed(pos),
+        };
+        match new_pos {
+            Some(new_pos) if new_pos >= self.start => {
+                self.pos = new_pos;
+                Ok(new_pos - self.start)
+            }
+            _ => Err(std::io::Error::new(ErrorKind::Other, "SeekBeforeStart")),
+        }
+    }
+}
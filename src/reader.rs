@@ -1,17 +1,140 @@
 use std::{
+    borrow::Borrow,
+    cell::{Cell, RefCell},
     fs::File,
     io::{ErrorKind, Read, Seek, SeekFrom},
     path::Path,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
 };
 
 use bit_set::BitSet;
 use crc::Crc;
 
-use crate::{archive::*, decoders::add_decoder, error::Error, folder::*, password::Password};
+use crate::{
+    archive::*,
+    decoders::{add_decoder, get_mem_usage_kb},
+    error::{Error, ExtractLimit},
+    folder::*,
+    password::Password,
+    progress::{is_cancelled, CancellationToken, PackedCountingReader, Progress, ProgressReader},
+    read_at::{ReadAt, ReadAtReader, SeekReadAt},
+};
 pub(crate) const CRC32: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
 const MAX_MEM_LIMIT_KB: usize = usize::MAX / 1024;
 
-pub(crate) trait SeedRead: Read + Seek {}
+/// Options for reading an archive.
+#[derive(Debug, Clone, Copy)]
+pub struct ReaderOptions {
+    /// The maximum memory in KiB the decoders of one block and the archive header may use.
+    /// Blocks that need more fail with [Error::MaxMemLimited] before anything is allocated.
+    /// The default is unlimited.
+    pub mem_limit_kb: usize,
+    /// The maximum number of bytes decoded from all entries together.
+    pub max_unpacked_size: Option<u64>,
+    /// The maximum number of entries in the archive.
+    pub max_entry_count: Option<usize>,
+    /// The maximum number of bytes decoded from one entry.
+    pub max_entry_size: Option<u64>,
+    /// The maximum ratio of the bytes decoded from a block to its packed size.
+    pub max_compression_ratio: Option<u64>,
+    /// The number of bytes to search for the signature header if the archive doesn't start
+    /// at the current position, e.g. after the stub of a self-extracting executable,
+    /// see [Archive::find_signature]. The default is 0, which doesn't search.
+    pub signature_search_limit: u64,
+    /// The number of bytes of a stream spooled in memory before moving to a temporary file,
+    /// see [SevenZReader::from_stream]. The default is 64 MiB.
+    pub spool_memory_limit: usize,
+}
+
+impl Default for ReaderOptions {
+    fn default() -> Self {
+        Self {
+            mem_limit_kb: MAX_MEM_LIMIT_KB,
+            max_unpacked_size: None,
+            max_entry_count: None,
+            max_entry_size: None,
+            max_compression_ratio: None,
+            signature_search_limit: 0,
+            spool_memory_limit: 64 * 1024 * 1024,
+        }
+    }
+}
+
+impl ReaderOptions {
+    #[inline]
+    pub fn with_mem_limit_kb(mut self, mem_limit_kb: usize) -> Self {
+        self.mem_limit_kb = mem_limit_kb;
+        self
+    }
+
+    #[inline]
+    pub fn with_max_unpacked_size(mut self, max_unpacked_size: u64) -> Self {
+        self.max_unpacked_size = Some(max_unpacked_size);
+        self
+    }
+
+    #[inline]
+    pub fn with_max_entry_count(mut self, max_entry_count: usize) -> Self {
+        self.max_entry_count = Some(max_entry_count);
+        self
+    }
+
+    #[inline]
+    pub fn with_max_entry_size(mut self, max_entry_size: u64) -> Self {
+        self.max_entry_size = Some(max_entry_size);
+        self
+    }
+
+    #[inline]
+    pub fn with_max_compression_ratio(mut self, max_compression_ratio: u64) -> Self {
+        self.max_compression_ratio = Some(max_compression_ratio);
+        self
+    }
+
+    #[inline]
+    pub fn with_signature_search_limit(mut self, signature_search_limit: u64) -> Self {
+        self.signature_search_limit = signature_search_limit;
+        self
+    }
+
+    #[inline]
+    pub fn with_spool_memory_limit(mut self, spool_memory_limit: usize) -> Self {
+        self.spool_memory_limit = spool_memory_limit;
+        self
+    }
+
+    fn check_entry_count(&self, entry_count: usize) -> Result<(), Error> {
+        match self.max_entry_count {
+            Some(max) if entry_count > max => {
+                Err(Error::LimitExceeded(ExtractLimit::EntryCount(max)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_mem_usage(&self, actaul_kb: usize) -> Result<(), Error> {
+        if actaul_kb > self.mem_limit_kb {
+            return Err(Error::MaxMemLimited {
+                max_kb: self.mem_limit_kb,
+                actaul_kb,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks the memory needed to decode `folder`, as far as it is known from the coder properties.
+    fn check_folder_mem_usage(&self, folder: &Folder) -> Result<(), Error> {
+        let mut mem_usage = 0usize;
+        for coder in folder.coders.iter() {
+            mem_usage = mem_usage.saturating_add(get_mem_usage_kb(coder)?);
+        }
+        self.check_mem_usage(mem_usage)
+    }
+}
 
 pub struct BoundedReader<R: Read> {
     inner: R,
@@ -59,8 +182,6 @@ pub struct SeekableBoundedReader<R: Read + Seek> {
     bounds: (u64, u64),
 }
 
-impl<R: Read + Seek> SeedRead for SeekableBoundedReader<R> {}
-
 impl<R: Read + Seek> Seek for SeekableBoundedReader<R> {
     fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
         let new_pos = match pos {
@@ -105,6 +226,65 @@ impl<R: Read + Seek> SeekableBoundedReader<R> {
     }
 }
 
+/// Counts the bytes decoded from a block and fails once a limit of [ReaderOptions] is exceeded.
+/// The counts don't rely on the sizes in the header.
+struct LimitingReader<R, T> {
+    inner: R,
+    options: ReaderOptions,
+    pack_size: u64,
+    block_read: u64,
+    entry_read: u64,
+    total_read: T,
+    exceeded: Option<ExtractLimit>,
+}
+
+impl<R: Read, T: Borrow<AtomicU64>> LimitingReader<R, T> {
+    fn new(inner: R, options: ReaderOptions, pack_size: u64, total_read: T) -> Self {
+        Self {
+            inner,
+            options,
+            pack_size,
+            block_read: 0,
+            entry_read: 0,
+            total_read,
+            exceeded: None,
+        }
+    }
+
+    fn check_limits(&self, size: u64) -> Option<ExtractLimit> {
+        let options = &self.options;
+        if let Some(max) = options.max_entry_size.filter(|max| self.entry_read > *max) {
+            return Some(ExtractLimit::EntrySize(max));
+        }
+        let total_read = self.total_read.borrow().fetch_add(size, Ordering::Relaxed) + size;
+        if let Some(max) = options.max_unpacked_size.filter(|max| total_read > *max) {
+            return Some(ExtractLimit::UnpackedSize(max));
+        }
+        options
+            .max_compression_ratio
+            .filter(|max| self.block_read > self.pack_size.saturating_mul(*max))
+            .map(ExtractLimit::CompressionRatio)
+    }
+}
+
+impl<R: Read, T: Borrow<AtomicU64>> Read for LimitingReader<R, T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.exceeded.is_none() {
+            let size = self.inner.read(buf)?;
+            self.block_read += size as u64;
+            self.entry_read += size as u64;
+            self.exceeded = self.check_limits(size as u64);
+            if self.exceeded.is_none() {
+                return Ok(size);
+            }
+        }
+        Err(std::io::Error::new(
+            ErrorKind::Other,
+            Error::LimitExceeded(self.exceeded.unwrap()),
+        ))
+    }
+}
+
 struct Crc32VerifyingReader<R> {
     inner: R,
     crc_digest: crc::Digest<'static, u32>,
@@ -188,6 +368,25 @@ impl Archive {
         reader_len: u64,
         password: &[u8],
     ) -> Result<Archive, Error> {
+        Self::read_with_options(reader, reader_len, password, ReaderOptions::default())
+    }
+
+    /// Like [read](Self::read), with options to limit the memory used for untrusted archives.
+    pub fn read_with_options<R: Read + Seek>(
+        reader: &mut R,
+        reader_len: u64,
+        password: &[u8],
+        options: ReaderOptions,
+    ) -> Result<Archive, Error> {
+        let mut signature_offset = reader.stream_position().map_err(Error::io)?;
+        if options.signature_search_limit > 0 {
+            if let Some(pos) = Self::find_signature(reader, options.signature_search_limit)? {
+                signature_offset = pos;
+            }
+            reader
+                .seek(SeekFrom::Start(signature_offset))
+                .map_err(Error::io)?;
+        }
         let mut signature = [0; 6];
         reader.read_exact(&mut signature).map_err(Error::io)?;
         if signature != SEVEN_Z_SIGNATURE {
@@ -217,11 +416,76 @@ impl Archive {
         } else {
             true
         };
-        if header_valid {
+        let archive = if header_valid {
             let start_header = Self::read_start_header(reader, start_header_crc)?;
-            Self::init_archive(reader, start_header, password, true)
+            Self::init_archive(
+                reader,
+                signature_offset,
+                start_header,
+                password,
+                true,
+                &options,
+            )?
         } else {
-            Self::try_to_locale_end_header(reader, reader_len, password)
+            Self::try_to_locale_end_header(reader, reader_len, password, &options)?
+        };
+        // the compression ratio limit relies on the pack sizes, they must fit in the input
+        let pack_end = archive
+            .pack_sizes
+            .iter()
+            .try_fold(archive.pack_pos, |end, size| end.checked_add(*size))
+            .and_then(|end| end.checked_add(archive.signature_offset + SIGNATURE_HEADER_SIZE));
+        if pack_end.map_or(true, |end| end > reader_len) {
+            return Err(Error::other(
+                "Packed streams extend beyond the end of the archive",
+            ));
+        }
+        Ok(archive)
+    }
+
+    /// Searches the next `search_limit` bytes from the current position of `reader`
+    /// for a signature header whose start header CRC is valid, and returns its position.
+    ///
+    /// Self-extracting executables and installers carry the archive after a stub,
+    /// which may contain the signature bytes itself, hence the CRC check.
+    pub fn find_signature<R: Read + Seek>(
+        reader: &mut R,
+        search_limit: u64,
+    ) -> Result<Option<u64>, Error> {
+        const CHUNK_SIZE: u64 = 64 * 1024;
+        let header_size = SIGNATURE_HEADER_SIZE as usize;
+        let mut buf_pos = reader.stream_position().map_err(Error::io)?;
+        let end = buf_pos.saturating_add(search_limit);
+        let mut buf = Vec::new();
+        loop {
+            let read = (&mut *reader)
+                .take(CHUNK_SIZE)
+                .read_to_end(&mut buf)
+                .map_err(Error::io)?;
+            // the positions whose whole signature header is in `buf`, unless at the end
+            let scan_len = if read == 0 {
+                buf.len()
+            } else {
+                buf.len().saturating_sub(header_size - 1)
+            };
+            for i in 0..scan_len {
+                if buf_pos + i as u64 >= end {
+                    return Ok(None);
+                }
+                let header = &buf[i..];
+                if header.len() >= header_size
+                    && header.starts_with(SEVEN_Z_SIGNATURE)
+                    && header[6] == 0
+                    && crc32_cksum(&header[12..header_size]).to_le_bytes() == header[8..12]
+                {
+                    return Ok(Some(buf_pos + i as u64));
+                }
+            }
+            if read == 0 {
+                return Ok(None);
+            }
+            buf.drain(..scan_len);
+            buf_pos += scan_len as u64;
         }
     }
 
@@ -288,9 +552,11 @@ impl Archive {
         reader: &mut R,
         reader_len: u64,
         password: &[u8],
+        options: &ReaderOptions,
     ) -> Result<Self, Error> {
         let search_limit = 1024 * 1024;
-        let prev_data_size = reader.stream_position().map_err(Error::io)? + 20;
+        let signature_offset = reader.stream_position().map_err(Error::io)? - 12;
+        let prev_data_size = signature_offset + SIGNATURE_HEADER_SIZE;
         let size = reader_len;
         let min_pos = if reader.stream_position().map_err(Error::io)? + search_limit > size {
             reader.stream_position().map_err(Error::io)?
@@ -311,7 +577,14 @@ impl Archive {
                     next_header_size: reader_len - pos,
                     next_header_crc: 0,
                 };
-                let result = Self::init_archive(reader, start_header, password, false)?;
+                let result = Self::init_archive(
+                    reader,
+                    signature_offset,
+                    start_header,
+                    password,
+                    false,
+                    options,
+                )?;
 
                 if !result.files.is_empty() {
                     return Ok(result);
@@ -325,9 +598,11 @@ impl Archive {
 
     fn init_archive<R: Read + Seek>(
         reader: &mut R,
+        signature_offset: u64,
         start_header: StartHeader,
         password: &[u8],
         verify_crc: bool,
+        options: &ReaderOptions,
     ) -> Result<Self, Error> {
         if start_header.next_header_size > usize::MAX as u64 {
             return Err(Error::other(format!(
@@ -337,10 +612,11 @@ impl Archive {
         }
 
         let next_header_size_int = start_header.next_header_size as usize;
+        options.check_mem_usage(next_header_size_int / 1024)?;
 
         reader
             .seek(SeekFrom::Start(
-                SIGNATURE_HEADER_SIZE + start_header.next_header_offset,
+                signature_offset + SIGNATURE_HEADER_SIZE + start_header.next_header_offset,
             ))
             .map_err(Error::io)?;
 
@@ -350,18 +626,30 @@ impl Archive {
             return Err(Error::NextHeaderCrcMismatch);
         }
 
-        let mut archive = Archive::default();
+        let mut archive = Archive {
+            signature_offset,
+            ..Default::default()
+        };
         let mut buf_reader = buf.as_slice();
         let mut nid = read_u8(&mut buf_reader)?;
         let mut header = if nid == K_ENCODED_HEADER {
-            let (mut out_reader, buf_size) =
-                Self::read_encoded_header(&mut buf_reader, reader, &mut archive, password)?;
+            let (mut out_reader, buf_size) = Self::read_encoded_header(
+                &mut buf_reader,
+                reader,
+                &mut archive,
+                password,
+                options,
+            )?;
+            options.check_mem_usage(buf_size / 1024)?;
             buf.clear();
             buf.resize(buf_size, 0);
             out_reader
                 .read_exact(&mut buf)
                 .map_err(|e| Error::bad_password(e, !password.is_empty()))?;
-            archive = Archive::default();
+            archive = Archive {
+                signature_offset,
+                ..Default::default()
+            };
             buf_reader = buf.as_slice();
             nid = read_u8(&mut buf_reader)?;
             buf_reader
@@ -382,14 +670,16 @@ impl Archive {
         reader: &'r mut RI,
         archive: &mut Archive,
         password: &[u8],
+        options: &ReaderOptions,
     ) -> Result<(Box<dyn Read + 'r>, usize), Error> {
         Self::read_streams_info(header, archive)?;
         let folder = archive
             .folders
             .first()
             .ok_or(Error::other("no folders, can't read encoded header"))?;
+        options.check_folder_mem_usage(folder)?;
         let first_pack_stream_index = 0;
-        let folder_offset = SIGNATURE_HEADER_SIZE + archive.pack_pos;
+        let folder_offset = archive.signature_offset + SIGNATURE_HEADER_SIZE + archive.pack_pos;
         if archive.pack_sizes.is_empty() {
             return Err(Error::other("no packed streams, can't read encoded header"));
         }
@@ -415,7 +705,7 @@ impl Archive {
                     folder.get_unpack_size_at_index(index) as usize,
                     coder,
                     password,
-                    MAX_MEM_LIMIT_KB,
+                    options.mem_limit_kb,
                 )?;
                 decoder = Box::new(next);
             }
@@ -1104,10 +1394,57 @@ impl<'a, R: Read> Iterator for NamesReader<'a, R> {
     }
 }
 
+/// The result of testing one entry with [SevenZReader::test].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestStatus {
+    Ok,
+    /// The data was decoded, but a pack stream, block or entry CRC doesn't match.
+    CrcMismatch,
+    /// The data couldn't be decoded, e.g. because it is corrupt or truncated.
+    DataError(String),
+    /// The entry is encrypted and the password is missing or wrong.
+    WrongPassword,
+}
+
+#[derive(Debug, Clone)]
+pub struct EntryTestResult {
+    pub name: String,
+    pub status: TestStatus,
+}
+
+/// Report of [SevenZReader::test], with one result per entry of [Archive::files].
+#[derive(Debug, Clone, Default)]
+pub struct TestReport {
+    pub entries: Vec<EntryTestResult>,
+}
+
+impl TestReport {
+    /// Whether all entries were decoded and verified.
+    pub fn is_ok(&self) -> bool {
+        self.entries.iter().all(|e| e.status == TestStatus::Ok)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &EntryTestResult> {
+        self.entries.iter().filter(|e| e.status != TestStatus::Ok)
+    }
+}
+
 pub struct SevenZReader<R: Read + Seek> {
-    source: R,
+    pub(crate) source: R,
     archive: Archive,
     password: Vec<u8>,
+    options: ReaderOptions,
+    /// In a [Mutex] to keep the reader [Sync], though it is only used through `&mut self`.
+    progress: Option<Mutex<Box<dyn Progress + Send>>>,
+    cancellation_token: Option<CancellationToken>,
+}
+
+fn progress_mut(
+    progress: &mut Option<Mutex<Box<dyn Progress + Send>>>,
+) -> Option<&mut dyn Progress> {
+    let progress = progress.as_mut()?;
+    let progress = progress.get_mut().unwrap_or_else(|e| e.into_inner());
+    Some(progress.as_mut())
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -1119,17 +1456,52 @@ impl SevenZReader<File> {
         let len = file.metadata().map(|m| m.len()).map_err(Error::io)?;
         Self::new(file, len, password)
     }
+
+    /// Like [open](Self::open), with options to limit the memory used for untrusted archives.
+    #[inline]
+    pub fn open_with_options(
+        path: impl AsRef<std::path::Path>,
+        password: Password,
+        options: ReaderOptions,
+    ) -> Result<Self, Error> {
+        let file = std::fs::File::open(path.as_ref())
+            .map_err(|e| Error::file_open(e, path.as_ref().to_string_lossy().to_string()))?;
+        let len = file.metadata().map(|m| m.len()).map_err(Error::io)?;
+        Self::new_with_options(file, len, password, options)
+    }
 }
 
 impl<R: Read + Seek> SevenZReader<R> {
     #[inline]
-    pub fn new(mut source: R, reader_len: u64, password: Password) -> Result<Self, Error> {
+    pub fn new(source: R, reader_len: u64, password: Password) -> Result<Self, Error> {
+        Self::new_with_options(source, reader_len, password, ReaderOptions::default())
+    }
+
+    /// Like [new](Self::new), with options to limit the memory used for untrusted archives.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use sevenz_rust::*;
+    /// let file = std::fs::File::open("upload.7z").unwrap();
+    /// let len = file.metadata().unwrap().len();
+    /// let options = ReaderOptions::default().with_mem_limit_kb(64 * 1024);
+    /// let sz = SevenZReader::new_with_options(file, len, Password::empty(), options).unwrap();
+    /// ```
+    pub fn new_with_options(
+        mut source: R,
+        reader_len: u64,
+        password: Password,
+        options: ReaderOptions,
+    ) -> Result<Self, Error> {
         let password = password.to_vec();
-        let archive = Archive::read(&mut source, reader_len, &password)?;
+        let archive = Archive::read_with_options(&mut source, reader_len, &password, options)?;
         Ok(Self {
             source,
             archive,
             password,
+            options,
+            progress: None,
+            cancellation_token: None,
         })
     }
 
@@ -1139,27 +1511,52 @@ impl<R: Read + Seek> SevenZReader<R> {
             source,
             archive,
             password: password.to_vec(),
+            options: Default::default(),
+            progress: None,
+            cancellation_token: None,
         }
     }
 
+    #[cfg(feature = "async")]
+    pub(crate) fn with_options(mut self, options: ReaderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
     #[inline]
     pub fn archive(&self) -> &Archive {
         &self.archive
     }
 
-    fn build_decode_stack<'r>(
-        source: &'r mut R,
+    /// Sets the receiver of the progress of [for_each_entries](Self::for_each_entries).
+    pub fn set_progress(&mut self, progress: impl Progress + Send + 'static) -> &mut Self {
+        self.progress = Some(Mutex::new(Box::new(progress)));
+        self
+    }
+
+    /// Sets the token to stop extracting with [Error::Cancelled],
+    /// which is checked before each read of the entry contents.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    pub(crate) fn build_decode_stack<'r, S: Read + Seek + 'r>(
+        mut source: S,
         archive: &Archive,
         folder_index: usize,
         password: &[u8],
+        options: &ReaderOptions,
     ) -> Result<(Box<dyn Read + 'r>, usize), Error> {
         let folder = &archive.folders[folder_index];
+        options.check_folder_mem_usage(folder)?;
         if folder.total_input_streams > folder.total_output_streams {
-            return Self::build_decode_stack2(source, archive, folder_index, password);
+            return Self::build_decode_stack2(source, archive, folder_index, password, options);
         }
         let first_pack_stream_index =
             archive.stream_map.folder_first_pack_stream_index[folder_index];
-        let folder_offset = SIGNATURE_HEADER_SIZE
+        let folder_offset = archive.signature_offset
+            + SIGNATURE_HEADER_SIZE
             + archive.pack_pos
             + archive.stream_map.pack_stream_offsets[first_pack_stream_index];
 
@@ -1181,7 +1578,7 @@ impl<R: Read + Seek> SevenZReader<R> {
                 folder.get_unpack_size_at_index(index) as usize,
                 coder,
                 password,
-                MAX_MEM_LIMIT_KB,
+                options.mem_limit_kb,
             )?;
             decoder = Box::new(next);
         }
@@ -1196,11 +1593,12 @@ impl<R: Read + Seek> SevenZReader<R> {
         Ok((decoder, pack_size))
     }
 
-    fn build_decode_stack2<'r>(
-        source: &'r mut R,
+    fn build_decode_stack2<'r, S: Read + Seek + 'r>(
+        source: S,
         archive: &Archive,
         folder_index: usize,
         password: &[u8],
+        options: &ReaderOptions,
     ) -> Result<(Box<dyn Read + 'r>, usize), Error> {
         const MAX_CODER_COUNT: usize = 32;
         let folder = &archive.folders[folder_index];
@@ -1212,18 +1610,18 @@ impl<R: Read + Seek> SevenZReader<R> {
         }
 
         assert!(folder.total_input_streams > folder.total_output_streams);
-        let source = ReaderPtr::new(source);
+        // the pack streams are read alternately, each with its own position in the shared source
+        let source = Rc::new(SeekReadAt::new(source));
         let first_pack_stream_index =
             archive.stream_map.folder_first_pack_stream_index[folder_index];
-        let start_pos = SIGNATURE_HEADER_SIZE + archive.pack_pos;
+        let start_pos = archive.signature_offset + SIGNATURE_HEADER_SIZE + archive.pack_pos;
         let offsets = &archive.stream_map.pack_stream_offsets[first_pack_stream_index..];
 
         let mut sources = Vec::with_capacity(folder.packed_streams.len());
         for i in 0..folder.packed_streams.len() {
             let pack_pos = start_pos + offsets[i];
             let pack_size = archive.pack_sizes[first_pack_stream_index + i];
-            let pack_reader =
-                SeekableBoundedReader::new(source.clone(), (pack_pos, pack_pos + pack_size));
+            let pack_reader = ReadAtReader::new(source.clone(), pack_pos, pack_pos + pack_size);
             sources.push(pack_reader);
         }
 
@@ -1264,6 +1662,7 @@ impl<R: Read + Seek> SevenZReader<R> {
                 &sources,
                 &coder_to_stream_map,
                 password,
+                options,
                 i,
             )?);
         }
@@ -1284,17 +1683,15 @@ impl<R: Read + Seek> SevenZReader<R> {
         ))
     }
 
-    fn get_in_stream<'r>(
+    fn get_in_stream<'r, S: Read + Clone + 'r>(
         folder: &Folder,
-        sources: &[SeekableBoundedReader<ReaderPtr<R>>],
+        sources: &[S],
         coder_to_stream_map: &[usize],
         password: &[u8],
+        options: &ReaderOptions,
 
         in_stream_index: usize,
-    ) -> Result<Box<dyn Read + 'r>, Error>
-    where
-        R: 'r,
-    {
+    ) -> Result<Box<dyn Read + 'r>, Error> {
         let index = folder
             .packed_streams
             .iter()
@@ -1313,19 +1710,24 @@ impl<R: Read + Seek> SevenZReader<R> {
             })?;
         let index = folder.bind_pairs[bp].out_index as usize;
 
-        Self::get_in_stream2(folder, sources, coder_to_stream_map, password, index)
+        Self::get_in_stream2(
+            folder,
+            sources,
+            coder_to_stream_map,
+            password,
+            options,
+            index,
+        )
     }
 
-    fn get_in_stream2<'r>(
+    fn get_in_stream2<'r, S: Read + Clone + 'r>(
         folder: &Folder,
-        sources: &[SeekableBoundedReader<ReaderPtr<R>>],
+        sources: &[S],
         coder_to_stream_map: &[usize],
         password: &[u8],
+        options: &ReaderOptions,
         in_stream_index: usize,
-    ) -> Result<Box<dyn Read + 'r>, Error>
-    where
-        R: 'r,
-    {
+    ) -> Result<Box<dyn Read + 'r>, Error> {
         let coder = &folder.coders[in_stream_index];
         let start_index = coder_to_stream_map[in_stream_index];
         if start_index == usize::MAX {
@@ -1333,10 +1735,22 @@ impl<R: Read + Seek> SevenZReader<R> {
         }
         let uncompressed_len = folder.unpack_sizes[in_stream_index] as usize;
         if coder.num_in_streams == 1 {
-            let input =
-                Self::get_in_stream(folder, sources, coder_to_stream_map, password, start_index)?;
+            let input = Self::get_in_stream(
+                folder,
+                sources,
+                coder_to_stream_map,
+                password,
+                options,
+                start_index,
+            )?;
 
-            let decoder = add_decoder(input, uncompressed_len, coder, password, MAX_MEM_LIMIT_KB)?;
+            let decoder = add_decoder(
+                input,
+                uncompressed_len,
+                coder,
+                password,
+                options.mem_limit_kb,
+            )?;
             return Ok(Box::new(decoder));
         }
         Err(Error::unsupported(
@@ -1344,6 +1758,37 @@ impl<R: Read + Seek> SevenZReader<R> {
         ))
     }
 
+    /// Returns a cursor to read the entries one after another in archive order,
+    /// as an alternative to the closure of [for_each_entries](Self::for_each_entries).
+    ///
+    /// The decoder of the current block is kept between the entries, skipped entries
+    /// are decoded and dropped. The [progress](Self::set_progress) isn't reported.
+    /// # Examples
+    /// ```no_run
+    /// use sevenz_rust::*;
+    /// use std::io::Read;
+    /// let mut sz = SevenZReader::open("path/to/archive.7z", Password::empty()).unwrap();
+    /// let mut entries = sz.entries();
+    /// while let Some((entry, mut reader)) = entries.next_entry().unwrap() {
+    ///     let mut content = Vec::new();
+    ///     reader.read_to_end(&mut content).unwrap();
+    ///     println!("{}: {} bytes", entry.name(), content.len());
+    /// }
+    /// ```
+    pub fn entries(&mut self) -> Entries<'_, R> {
+        Entries {
+            archive: &self.archive,
+            password: &self.password,
+            options: self.options,
+            cancellation_token: self.cancellation_token.as_ref(),
+            source: Rc::new(RefCell::new(&mut self.source)),
+            total_read: Arc::new(AtomicU64::new(0)),
+            block: None,
+            next_file: 0,
+            finished: false,
+        }
+    }
+
     /// Takes a closure to decode each files in the archive.
     ///
     /// Attention about solid archive:
@@ -1353,35 +1798,414 @@ impl<R: Read + Seek> SevenZReader<R> {
     /// See [ChecksumVerificationFailed](https://github.com/dyz1990/sevenz-rust/issues/31).
     ///
     /// To speed up decompression, you can check this example [examples/forder_dec.rs](https://github.com/dyz1990/sevenz-rust/blob/main/examples/forder_dec.rs).
-    /// And this example [mt_decompress.rs](https://github.com/dyz1990/sevenz-rust/blob/main/examples/mt_decompress.rs) if you want use multi-thread.
+    /// And [par_for_each_entries](Self::par_for_each_entries) if you want use multi-thread.
     ///
     pub fn for_each_entries<F: FnMut(&SevenZArchiveEntry, &mut dyn Read) -> Result<bool, Error>>(
         &mut self,
         mut each: F,
     ) -> Result<(), Error> {
+        self.options.check_entry_count(self.archive.files.len())?;
+        let total_read = AtomicU64::new(0);
         let folder_count = self.archive.folders.len();
         for folder_index in 0..folder_count {
-            let forder_dec = BlockDecoder::new(
+            let mut forder_dec = BlockDecoder::new_with_options(
                 folder_index,
                 &self.archive,
                 &self.password,
                 &mut self.source,
-            );
+                self.options,
+            )
+            .with_total_read(&total_read);
+            forder_dec.progress = progress_mut(&mut self.progress);
+            forder_dec.cancellation_token = self.cancellation_token.as_ref();
             forder_dec.for_each_entries(&mut each)?;
         }
         // decode empty files
         for file_index in 0..self.archive.files.len() {
             let folder_index = self.archive.stream_map.file_folder_index[file_index];
             if folder_index.is_none() {
+                if is_cancelled(self.cancellation_token.as_ref()) {
+                    return Err(Error::Cancelled);
+                }
                 let file = &self.archive.files[file_index];
+                if let Some(progress) = progress_mut(&mut self.progress) {
+                    progress.entry_started(file);
+                }
                 let empty_reader: &mut dyn Read = &mut ([0u8; 0].as_slice());
-                if !each(file, empty_reader)? {
+                let next = each(file, empty_reader)?;
+                if let Some(progress) = progress_mut(&mut self.progress) {
+                    progress.entry_finished(file);
+                }
+                if !next {
                     return Ok(());
                 }
             }
         }
         Ok(())
     }
+
+    /// Like [for_each_entries](Self::for_each_entries), but decodes the blocks of the archive
+    /// concurrently on up to `threads` threads.
+    ///
+    /// Each thread reads from its own source returned by `open_source`, e.g. the archive file
+    /// opened again. Entries of one block are passed to `each` in order, entries of different
+    /// blocks in any order. The first error or `Ok(false)` stops the other threads
+    /// at their next entry, and the first error is returned.
+    /// The [cancellation token](Self::set_cancellation_token) is honored,
+    /// the [progress](Self::set_progress) isn't reported.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use sevenz_rust::*;
+    /// let sz = SevenZReader::open("example.7z", Password::empty()).unwrap();
+    /// sz.par_for_each_entries(4, || std::fs::File::open("example.7z"), |entry, reader| {
+    ///     let size = std::io::copy(reader, &mut std::io::sink())?;
+    ///     println!("{}: {}", entry.name(), size);
+    ///     Ok(true)
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn par_for_each_entries<S, T, F>(
+        &self,
+        threads: usize,
+        open_source: S,
+        each: F,
+    ) -> Result<(), Error>
+    where
+        S: Fn() -> std::io::Result<T> + Sync,
+        T: Read + Seek,
+        F: Fn(&SevenZArchiveEntry, &mut dyn Read) -> Result<bool, Error> + Sync,
+    {
+        let archive = &self.archive;
+        let password = self.password.as_slice();
+        let options = self.options;
+        let cancellation_token = self.cancellation_token.as_ref();
+        options.check_entry_count(archive.files.len())?;
+        let total_read = AtomicU64::new(0);
+        let next_folder = AtomicUsize::new(0);
+        let stopped = AtomicBool::new(false);
+        let first_error = Mutex::new(None);
+        let stop = |result: Result<bool, Error>| {
+            if let Err(e) = result {
+                first_error.lock().unwrap().get_or_insert(e);
+            }
+            stopped.store(true, Ordering::Relaxed);
+        };
+        let decode_folders = || {
+            let mut source = None;
+            loop {
+                let folder_index = next_folder.fetch_add(1, Ordering::Relaxed);
+                if folder_index >= archive.folders.len() || stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+                let source = match &mut source {
+                    Some(source) => source,
+                    None => match open_source() {
+                        Ok(s) => source.insert(s),
+                        Err(e) => return stop(Err(Error::io(e))),
+                    },
+                };
+                let mut decoder = BlockDecoder::new_with_options(
+                    folder_index,
+                    archive,
+                    password,
+                    source,
+                    options,
+                )
+                .with_total_read(&total_read);
+                decoder.cancellation_token = cancellation_token;
+                let result = decoder.for_each_entries(&mut |entry, reader| {
+                    if stopped.load(Ordering::Relaxed) {
+                        return Ok(false);
+                    }
+                    each(entry, reader)
+                });
+                if !matches!(result, Ok(true)) {
+                    return stop(result);
+                }
+            }
+        };
+        std::thread::scope(|scope| {
+            for _ in 1..threads.min(archive.folders.len()) {
+                scope.spawn(decode_folders);
+            }
+            decode_folders();
+        });
+        if let Some(e) = first_error.into_inner().unwrap() {
+            return Err(e);
+        }
+        if stopped.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        // decode empty files
+        for (file_index, file) in archive.files.iter().enumerate() {
+            if archive.stream_map.file_folder_index[file_index].is_some() {
+                continue;
+            }
+            if is_cancelled(cancellation_token) {
+                return Err(Error::Cancelled);
+            }
+            if !each(file, &mut ([0u8; 0].as_slice()))? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a reader for the content of the entry named `name`.
+    ///
+    /// Only the block containing the entry is decoded, see [read_entry_at](Self::read_entry_at).
+    pub fn read_entry(&mut self, name: &str) -> Result<impl Read + '_, Error> {
+        let file_index = self
+            .archive
+            .files
+            .iter()
+            .position(|f| f.name() == name)
+            .ok_or_else(|| Error::other(format!("entry not found: {}", name)))?;
+        self.read_entry_at(file_index)
+    }
+
+    /// Returns a reader for the content of the entry at `file_index` in [Archive::files].
+    ///
+    /// Only the block containing the entry is decoded. In a solid block the data of the
+    /// entries in front of it still has to be decompressed, it is skipped internally.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use sevenz_rust::*;
+    /// let mut sz = SevenZReader::open("example.7z", Password::empty()).unwrap();
+    /// let content = std::io::read_to_string(sz.read_entry("dir/file.txt").unwrap()).unwrap();
+    /// ```
+    pub fn read_entry_at(&mut self, file_index: usize) -> Result<impl Read + '_, Error> {
+        let archive = &self.archive;
+        let file = archive
+            .files
+            .get(file_index)
+            .ok_or_else(|| Error::other(format!("entry index out of range: {}", file_index)))?;
+        let folder_index = match archive.stream_map.file_folder_index[file_index] {
+            Some(folder_index) if file.has_stream && file.size > 0 => folder_index,
+            _ => {
+                let empty_reader: Box<dyn Read + '_> = Box::new([0u8; 0].as_slice());
+                return Ok(empty_reader);
+            }
+        };
+        let encrypted = !self.password.is_empty();
+        let (mut folder_reader, _size) = Self::build_decode_stack(
+            &mut self.source,
+            archive,
+            folder_index,
+            &self.password,
+            &self.options,
+        )?;
+
+        let start = archive.stream_map.folder_first_file_index[folder_index];
+        let skip: u64 = archive.files[start..file_index]
+            .iter()
+            .filter(|f| f.has_stream)
+            .map(|f| f.size)
+            .sum();
+        if skip > 0 {
+            let skipped = std::io::copy(&mut (&mut folder_reader).take(skip), &mut std::io::sink())
+                .map_err(|e| Error::io(e).maybe_bad_password(encrypted))?;
+            if skipped != skip {
+                return Err(Error::other(format!(
+                    "unexpected end of block before entry: {}",
+                    file.name()
+                )));
+            }
+        }
+
+        let mut decoder: Box<dyn Read + '_> =
+            Box::new(BoundedReader::new(folder_reader, file.size as usize));
+        if file.has_crc {
+            decoder = Box::new(Crc32VerifyingReader::new(
+                decoder,
+                file.size as usize,
+                file.crc,
+            ));
+        }
+        Ok(decoder)
+    }
+
+    /// Decodes all entries and verifies the CRCs of the pack streams, blocks and entries,
+    /// like `7z t`.
+    ///
+    /// Unlike [for_each_entries](Self::for_each_entries) this doesn't stop at the first
+    /// failure, the report has a status for every entry.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use sevenz_rust::*;
+    /// let mut sz = SevenZReader::open("example.7z", Password::empty()).unwrap();
+    /// let report = sz.test().unwrap();
+    /// for entry in report.failures() {
+    ///     println!("{}: {:?}", entry.name, entry.status);
+    /// }
+    /// ```
+    pub fn test(&mut self) -> Result<TestReport, Error> {
+        let archive = &self.archive;
+        let mut folder_files = vec![Vec::new(); archive.folders.len()];
+        for (i, file) in archive.files.iter().enumerate() {
+            if let (true, Some(folder_index)) =
+                (file.has_stream, archive.stream_map.file_folder_index[i])
+            {
+                folder_files[folder_index].push(i);
+            }
+        }
+        let mut entries = archive
+            .files
+            .iter()
+            .map(|f| EntryTestResult {
+                name: f.name().to_string(),
+                status: TestStatus::Ok,
+            })
+            .collect::<Vec<_>>();
+        for (folder_index, file_indices) in folder_files.iter().enumerate() {
+            let statuses = self.test_folder(folder_index, file_indices)?;
+            for (i, status) in file_indices.iter().zip(statuses) {
+                entries[*i].status = status;
+            }
+        }
+        Ok(TestReport { entries })
+    }
+
+    fn test_folder(
+        &mut self,
+        folder_index: usize,
+        file_indices: &[usize],
+    ) -> Result<Vec<TestStatus>, Error> {
+        let archive = &self.archive;
+        let folder = &archive.folders[folder_index];
+        // garbage from a wrong key fails like corrupt data
+        let encrypted = folder
+            .coders
+            .iter()
+            .any(|c| c.decompression_method_id() == SevenZMethod::ID_AES256SHA256);
+        let data_error = |msg: String| {
+            if encrypted {
+                TestStatus::WrongPassword
+            } else {
+                TestStatus::DataError(msg)
+            }
+        };
+        let crc_mismatch = || {
+            if encrypted {
+                TestStatus::WrongPassword
+            } else {
+                TestStatus::CrcMismatch
+            }
+        };
+        let all = |status: TestStatus| Ok(vec![status; file_indices.len()]);
+
+        let first_pack_stream_index =
+            archive.stream_map.folder_first_pack_stream_index[folder_index];
+        for i in first_pack_stream_index..first_pack_stream_index + folder.packed_streams.len() {
+            if !archive.pack_crcs_defined.contains(i) {
+                continue;
+            }
+            let pack_pos = archive.signature_offset
+                + SIGNATURE_HEADER_SIZE
+                + archive.pack_pos
+                + archive.stream_map.pack_stream_offsets[i];
+            self.source
+                .seek(SeekFrom::Start(pack_pos))
+                .map_err(Error::io)?;
+            let pack_size = archive.pack_sizes[i];
+            match crc32_of((&mut self.source).take(pack_size)) {
+                Ok((_, size)) if size != pack_size => {
+                    return all(TestStatus::DataError(format!(
+                        "pack stream {} is truncated",
+                        i
+                    )))
+                }
+                Ok((crc, _)) if crc as u64 != archive.pack_crcs[i] => {
+                    return all(TestStatus::CrcMismatch)
+                }
+                Ok(_) => {}
+                Err(e) => return Err(Error::io(e)),
+            }
+        }
+
+        let (mut folder_reader, _) = match Self::build_decode_stack(
+            &mut self.source,
+            archive,
+            folder_index,
+            &self.password,
+            &self.options,
+        ) {
+            Ok(r) => r,
+            Err(Error::PasswordRequired) => return all(TestStatus::WrongPassword),
+            Err(e) => return all(TestStatus::DataError(e.to_string())),
+        };
+        let mut statuses = Vec::with_capacity(file_indices.len());
+        for &i in file_indices {
+            let file = &archive.files[i];
+            let status = match crc32_of((&mut folder_reader).take(file.size)) {
+                Ok((_, size)) if size != file.size => data_error("unexpected end of data".into()),
+                Ok((crc, _)) if file.has_crc && crc as u64 != file.crc => crc_mismatch(),
+                Ok(_) => TestStatus::Ok,
+                Err(e) => {
+                    let checksum_error = e
+                        .get_ref()
+                        .and_then(|e| e.downcast_ref::<Error>())
+                        .is_some_and(|e| matches!(e, Error::ChecksumVerificationFailed));
+                    statuses.push(if checksum_error {
+                        crc_mismatch()
+                    } else {
+                        data_error(e.to_string())
+                    });
+                    // the rest of the block can't be decoded
+                    let status = data_error(format!("not decoded after error: {}", e));
+                    statuses.resize(file_indices.len(), status);
+                    break;
+                }
+            };
+            statuses.push(status);
+        }
+        Ok(statuses)
+    }
+}
+
+/// Returns the CRC32 and length of the data read from `reader`.
+fn crc32_of(mut reader: impl Read) -> std::io::Result<(u32, u64)> {
+    let mut digest = CRC32.digest();
+    let mut buf = [0u8; 8192];
+    let mut len = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok((digest.finalize(), len)),
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        digest.update(&buf[..n]);
+        len += n as u64;
+    }
+}
+
+impl<R: Read + Seek + ReadAt + Sync> SevenZReader<R> {
+    /// Like [par_for_each_entries](Self::par_for_each_entries), but all threads read the
+    /// source of this reader through [ReadAt], each at its own position.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use sevenz_rust::*;
+    /// let sz = SevenZReader::open("example.7z", Password::empty()).unwrap();
+    /// sz.par_for_each_entries_shared(4, |entry, reader| {
+    ///     let size = std::io::copy(reader, &mut std::io::sink())?;
+    ///     println!("{}: {}", entry.name(), size);
+    ///     Ok(true)
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn par_for_each_entries_shared<F>(&self, threads: usize, each: F) -> Result<(), Error>
+    where
+        F: Fn(&SevenZArchiveEntry, &mut dyn Read) -> Result<bool, Error> + Sync,
+    {
+        let source = &self.source;
+        self.par_for_each_entries(threads, || Ok(ReadAtReader::new(source, 0, u64::MAX)), each)
+    }
 }
 
 /// Alias for ['BlockDecoder'], used for compatibility purposes.
@@ -1394,6 +2218,11 @@ pub struct BlockDecoder<'a, R: Read + Seek> {
     archive: &'a Archive,
     password: &'a [u8],
     source: &'a mut R,
+    options: ReaderOptions,
+    /// Bytes decoded so far from all blocks, for [ReaderOptions::max_unpacked_size].
+    total_read: Option<&'a AtomicU64>,
+    progress: Option<&'a mut dyn Progress>,
+    cancellation_token: Option<&'a CancellationToken>,
 }
 
 impl<'a, R: Read + Seek> BlockDecoder<'a, R> {
@@ -1402,15 +2231,49 @@ impl<'a, R: Read + Seek> BlockDecoder<'a, R> {
         archive: &'a Archive,
         password: &'a [u8],
         source: &'a mut R,
+    ) -> Self {
+        Self::new_with_options(folder_index, archive, password, source, Default::default())
+    }
+
+    /// Like [new](Self::new), with options to limit the memory used for untrusted archives.
+    pub fn new_with_options(
+        folder_index: usize,
+        archive: &'a Archive,
+        password: &'a [u8],
+        source: &'a mut R,
+        options: ReaderOptions,
     ) -> Self {
         Self {
             folder_index,
             archive,
             password,
             source,
+            options,
+            total_read: None,
+            progress: None,
+            cancellation_token: None,
         }
     }
 
+    /// Reports the progress of [for_each_entries](Self::for_each_entries) to `progress`.
+    pub fn with_progress(mut self, progress: &'a mut dyn Progress) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Stops [for_each_entries](Self::for_each_entries) with [Error::Cancelled]
+    /// once `token` is cancelled.
+    pub fn with_cancellation_token(mut self, token: &'a CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// Counts the decoded bytes of this block into `total_read`, which is shared between blocks.
+    fn with_total_read(mut self, total_read: &'a AtomicU64) -> Self {
+        self.total_read = Some(total_read);
+        self
+    }
+
     pub fn entries(&self) -> &[SevenZArchiveEntry] {
         let start = self.archive.stream_map.folder_first_file_index[self.folder_index];
         let file_count = self.archive.folders[self.folder_index].num_unpack_sub_streams;
@@ -1429,7 +2292,7 @@ impl<'a, R: Read + Seek> BlockDecoder<'a, R> {
     /// See [ChecksumVerificationFailed](https://github.com/dyz1990/sevenz-rust/issues/31).
     ///
     /// To speed up decompression, you can check this example [examples/forder_dec.rs](https://github.com/dyz1990/sevenz-rust/blob/main/examples/forder_dec.rs).
-    /// And this example [mt_decompress.rs](https://github.com/dyz1990/sevenz-rust/blob/main/examples/mt_decompress.rs) if you want use multi-thread.
+    /// And [SevenZReader::par_for_each_entries] if you want use multi-thread.
     ///
     pub fn for_each_entries<F: FnMut(&SevenZArchiveEntry, &mut dyn Read) -> Result<bool, Error>>(
         self,
@@ -1440,15 +2303,41 @@ impl<'a, R: Read + Seek> BlockDecoder<'a, R> {
             archive,
             password,
             source,
+            options,
+            total_read,
+            progress,
+            cancellation_token,
         } = self;
-        let (mut folder_reader, _size) =
-            SevenZReader::build_decode_stack(source, archive, folder_index, password)?;
+        let packed = Cell::new(0);
+        let mut source = PackedCountingReader::new(source, &packed);
+        let (folder_reader, _size) = SevenZReader::<R>::build_decode_stack(
+            &mut source,
+            archive,
+            folder_index,
+            password,
+            &options,
+        )?;
         let start = archive.stream_map.folder_first_file_index[folder_index];
         let file_count = archive.folders[folder_index].num_unpack_sub_streams;
+        let pack_size = block_pack_size(archive, folder_index);
+        let block_total_read = AtomicU64::new(0);
+        let folder_reader = LimitingReader::new(
+            folder_reader,
+            options,
+            pack_size,
+            total_read.unwrap_or(&block_total_read),
+        );
+        let mut folder_reader =
+            ProgressReader::new(folder_reader, progress, cancellation_token, &packed);
 
         for file_index in start..(file_count + start) {
+            if folder_reader.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
             let file = &archive.files[file_index];
+            folder_reader.entry_started(file);
             if file.has_stream && file.size > 0 {
+                folder_reader.inner.entry_read = 0;
                 let mut decoder: Box<dyn Read> =
                     Box::new(BoundedReader::new(&mut folder_reader, file.size as usize));
                 if file.has_crc {
@@ -1458,51 +2347,196 @@ impl<'a, R: Read + Seek> BlockDecoder<'a, R> {
                         file.crc,
                     ));
                 }
-                if !each(file, &mut decoder)
-                    .map_err(|e| e.maybe_bad_password(!self.password.is_empty()))?
-                {
+                let result = each(file, &mut decoder);
+                drop(decoder);
+                // also if `each` swallowed the error of the reader
+                if let Some(limit) = folder_reader.inner.exceeded {
+                    return Err(Error::LimitExceeded(limit));
+                }
+                if folder_reader.is_cancelled() {
+                    return Err(Error::Cancelled);
+                }
+                let next = result.map_err(|e| e.maybe_bad_password(!password.is_empty()))?;
+                folder_reader.entry_finished(file);
+                if !next {
+                    folder_reader.report(0);
                     return Ok(false);
                 }
             } else {
                 let empty_reader: &mut dyn Read = &mut ([0u8; 0].as_slice());
-                if !each(file, empty_reader)? {
+                let next = each(file, empty_reader)?;
+                folder_reader.entry_finished(file);
+                if !next {
                     return Ok(false);
                 }
             }
         }
+        // the decoders may stop before the end of the packed streams, e.g. an end marker
+        packed.set(packed.get().max(pack_size));
+        folder_reader.report(0);
         Ok(true)
     }
 }
 
-#[derive(Debug, Copy)]
-struct ReaderPtr<R> {
-    reader: *mut R,
+/// The packed size of the block `folder_index`.
+fn block_pack_size(archive: &Archive, folder_index: usize) -> u64 {
+    let first_pack_stream_index = archive.stream_map.folder_first_pack_stream_index[folder_index];
+    archive.pack_sizes[first_pack_stream_index..]
+        .iter()
+        .take(archive.folders[folder_index].packed_streams.len())
+        .fold(0, |sum, size| sum.saturating_add(*size))
 }
 
-impl<R> Clone for ReaderPtr<R> {
-    fn clone(&self) -> Self {
-        Self {
-            reader: self.reader,
-        }
+/// The source of a [SevenZReader], shared with the decoder of the block read by [Entries].
+struct SharedSource<'a, R>(Rc<RefCell<&'a mut R>>);
+
+impl<'a, R: Read> Read for SharedSource<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().read(buf)
     }
 }
 
-impl<R> ReaderPtr<R> {
-    fn new(reader: &mut R) -> Self {
-        Self {
-            reader: reader as *mut R,
-        }
+impl<'a, R: Seek> Seek for SharedSource<'a, R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.0.borrow_mut().seek(pos)
     }
 }
 
-impl<R: Read> Read for ReaderPtr<R> {
+/// The block read by [Entries], bounded to the current entry.
+struct BlockCursor<'a> {
+    folder_index: usize,
+    reader: LimitingReader<Box<dyn Read + 'a>, Arc<AtomicU64>>,
+    /// The bytes of the current entry not read yet.
+    remaining: u64,
+}
+
+impl<'a> Read for BlockCursor<'a> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        unsafe { (*self.reader).read(buf) }
+        let len = (buf.len() as u64).min(self.remaining) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+        let size = self.reader.read(&mut buf[..len])?;
+        self.remaining -= size as u64;
+        Ok(size)
     }
 }
 
-impl<R: Seek> Seek for ReaderPtr<R> {
-    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
-        unsafe { (*self.reader).seek(pos) }
+/// The entries of a [SevenZReader], see [SevenZReader::entries].
+pub struct Entries<'a, R> {
+    archive: &'a Archive,
+    password: &'a [u8],
+    options: ReaderOptions,
+    cancellation_token: Option<&'a CancellationToken>,
+    source: Rc<RefCell<&'a mut R>>,
+    /// Bytes decoded so far from all blocks, for [ReaderOptions::max_unpacked_size].
+    total_read: Arc<AtomicU64>,
+    block: Option<BlockCursor<'a>>,
+    next_file: usize,
+    finished: bool,
+}
+
+impl<'a, R: Read + Seek> Entries<'a, R> {
+    /// Skips the rest of the current entry and returns the next one with the reader
+    /// of its content, or `None` after the last entry.
+    ///
+    /// After an error, `None` is returned.
+    pub fn next_entry(
+        &mut self,
+    ) -> Result<Option<(&'a SevenZArchiveEntry, EntryReader<'_>)>, Error> {
+        if self.finished {
+            return Ok(None);
+        }
+        let file_index = match self.advance() {
+            Ok(Some(file_index)) => file_index,
+            result => {
+                self.finished = true;
+                return result.map(|_| None);
+            }
+        };
+        let archive = self.archive;
+        let file = &archive.files[file_index];
+        let has_block = archive.stream_map.file_folder_index[file_index].is_some();
+        let inner: Box<dyn Read + '_> = match &mut self.block {
+            Some(block) if has_block => {
+                if file.has_crc {
+                    Box::new(Crc32VerifyingReader::new(
+                        block,
+                        file.size as usize,
+                        file.crc,
+                    ))
+                } else {
+                    Box::new(block)
+                }
+            }
+            _ => Box::new([0u8; 0].as_slice()),
+        };
+        Ok(Some((file, EntryReader { inner })))
+    }
+
+    /// Skips the rest of the current entry and moves to the next one, returns its index.
+    fn advance(&mut self) -> Result<Option<usize>, Error> {
+        if self.next_file == 0 {
+            self.options.check_entry_count(self.archive.files.len())?;
+        }
+        if let Some(block) = &mut self.block {
+            let skipped = std::io::copy(block, &mut std::io::sink());
+            // also if the caller swallowed the error of the reader
+            if let Some(limit) = block.reader.exceeded {
+                return Err(Error::LimitExceeded(limit));
+            }
+            skipped.map_err(Error::io)?;
+            if block.remaining > 0 {
+                return Err(Error::io(ErrorKind::UnexpectedEof.into()));
+            }
+        }
+        if is_cancelled(self.cancellation_token) {
+            return Err(Error::Cancelled);
+        }
+        let file_index = self.next_file;
+        let Some(file) = self.archive.files.get(file_index) else {
+            return Ok(None);
+        };
+        self.next_file += 1;
+        let Some(folder_index) = self.archive.stream_map.file_folder_index[file_index] else {
+            return Ok(Some(file_index));
+        };
+        if self.block.as_ref().map(|block| block.folder_index) != Some(folder_index) {
+            // the decoder of the previous block releases the source
+            self.block = None;
+            let (decoder, _) = SevenZReader::<R>::build_decode_stack(
+                SharedSource(self.source.clone()),
+                self.archive,
+                folder_index,
+                self.password,
+                &self.options,
+            )?;
+            let pack_size = block_pack_size(self.archive, folder_index);
+            self.block = Some(BlockCursor {
+                folder_index,
+                reader: LimitingReader::new(
+                    decoder,
+                    self.options,
+                    pack_size,
+                    self.total_read.clone(),
+                ),
+                remaining: 0,
+            });
+        }
+        let block = self.block.as_mut().unwrap();
+        block.reader.entry_read = 0;
+        block.remaining = file.size;
+        Ok(Some(file_index))
+    }
+}
+
+/// The content of an entry returned by [Entries::next_entry].
+pub struct EntryReader<'r> {
+    inner: Box<dyn Read + 'r>,
+}
+
+impl<'r> Read for EntryReader<'r> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
     }
 }
@@ -0,0 +1,295 @@
+//! Reading archives from streams that can't seek, like stdin or a network stream.
+use std::{
+    fs::File,
+    io::{Chain, Cursor, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    archive::{SEVEN_Z_SIGNATURE, SIGNATURE_HEADER_SIZE},
+    reader::CRC32,
+    Error, Password, ReaderOptions, SevenZReader,
+};
+
+/// A stream copied into memory, or into a temporary file once it exceeds a limit,
+/// to read it as [Read] + [Seek].
+pub struct SpooledInput {
+    spool: Spool,
+    len: u64,
+}
+
+enum Spool {
+    Memory(Cursor<Vec<u8>>),
+    File(TempFile),
+}
+
+/// A temporary file only readable by its owner, which is removed when dropped.
+struct TempFile {
+    file: File,
+    path: PathBuf,
+}
+
+impl TempFile {
+    fn create() -> std::io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        loop {
+            let path = std::env::temp_dir().join(format!(
+                ".sevenz-spool-{}-{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            let mut options = std::fs::OpenOptions::new();
+            options.read(true).write(true).create_new(true);
+            // only the owner can read the spooled archive in the shared temporary directory
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            match options.open(&path) {
+                Ok(file) => return Ok(Self { file, path }),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl SpooledInput {
+    /// Reads `reader` to the end, keeping up to `memory_limit` bytes in memory
+    /// before moving them to a temporary file.
+    pub fn new(mut reader: impl Read, memory_limit: usize) -> std::io::Result<Self> {
+        let mut buf = Vec::new();
+        let limit = memory_limit as u64 + 1;
+        let read = (&mut reader).take(limit).read_to_end(&mut buf)? as u64;
+        if read < limit {
+            return Ok(Self {
+                len: read,
+                spool: Spool::Memory(Cursor::new(buf)),
+            });
+        }
+        let mut temp = TempFile::create()?;
+        temp.file.write_all(&buf)?;
+        drop(buf);
+        let len = read + std::io::copy(&mut reader, &mut temp.file)?;
+        temp.file.seek(SeekFrom::Start(0))?;
+        Ok(Self {
+            len,
+            spool: Spool::File(temp),
+        })
+    }
+
+    /// Returns the length of the stream.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns whether the stream was moved to a temporary file.
+    pub fn is_in_file(&self) -> bool {
+        matches!(self.spool, Spool::File(_))
+    }
+}
+
+impl Read for SpooledInput {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &mut self.spool {
+            Spool::Memory(cursor) => cursor.read(buf),
+            Spool::File(temp) => temp.file.read(buf),
+        }
+    }
+}
+
+impl Seek for SpooledInput {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match &mut self.spool {
+            Spool::Memory(cursor) => cursor.seek(pos),
+            Spool::File(temp) => temp.file.seek(pos),
+        }
+    }
+}
+
+/// Reads a stream forward, skipping the bytes up to the positions seeked to.
+struct ForwardReader<R> {
+    inner: R,
+    pos: u64,
+}
+
+impl<R: Read> Read for ForwardReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.pos += size as u64;
+        Ok(size)
+    }
+}
+
+impl<R: Read> Seek for ForwardReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(pos) => self.pos.checked_add_signed(pos),
+            SeekFrom::End(_) => None,
+        };
+        let new_pos = new_pos
+            .filter(|new_pos| *new_pos >= self.pos)
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    ErrorKind::Unsupported,
+                    "a header-first archive read from a stream can only be read forward once",
+                )
+            })?;
+        let skip = new_pos - self.pos;
+        let skipped = std::io::copy(&mut (&mut self.inner).take(skip), &mut std::io::sink())?;
+        self.pos += skipped;
+        if skipped != skip {
+            return Err(std::io::Error::from(ErrorKind::UnexpectedEof));
+        }
+        Ok(self.pos)
+    }
+}
+
+/// The input of an archive read from a stream, see [SevenZReader::from_stream].
+pub struct StreamInput<R> {
+    input: Input<R>,
+}
+
+enum Input<R> {
+    Forward(ForwardReader<Chain<Cursor<Vec<u8>>, R>>),
+    /// The stream spooled from the position after the first one.
+    Spooled(SpooledInput, u64),
+}
+
+impl<R: Read> StreamInput<R> {
+    /// Spools the rest of a stream that is read forward, to be able to seek back in it.
+    fn spool_rest(&mut self, memory_limit: usize) -> std::io::Result<()> {
+        if let Input::Forward(reader) = &mut self.input {
+            let start = reader.pos;
+            let spooled = SpooledInput::new(&mut reader.inner, memory_limit)?;
+            self.input = Input::Spooled(spooled, start);
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for StreamInput<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &mut self.input {
+            Input::Forward(reader) => reader.read(buf),
+            Input::Spooled(spooled, _) => spooled.read(buf),
+        }
+    }
+}
+
+impl<R: Read> Seek for StreamInput<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match &mut self.input {
+            Input::Forward(reader) => reader.seek(pos),
+            Input::Spooled(spooled, start) => {
+                let pos = match pos {
+                    SeekFrom::Start(pos) => {
+                        SeekFrom::Start(pos.checked_sub(*start).ok_or_else(|| {
+                            std::io::Error::new(ErrorKind::InvalidInput, "SeekBeforeStart")
+                        })?)
+                    }
+                    pos => pos,
+                };
+                Ok(spooled.seek(pos)? + *start)
+            }
+        }
+    }
+}
+
+/// Whether `header` is a valid signature header pointing to the header right after it.
+fn is_header_first(header: &[u8]) -> bool {
+    header.len() == SIGNATURE_HEADER_SIZE as usize
+        && header.starts_with(SEVEN_Z_SIGNATURE)
+        && header[6] == 0
+        && header[8..12] != [0; 4]
+        && CRC32.checksum(&header[12..]).to_le_bytes() == header[8..12]
+        && header[12..20] == [0; 8]
+}
+
+impl<R: Read> SevenZReader<StreamInput<R>> {
+    /// Reads an archive from `reader`, which doesn't need to be [Seek].
+    ///
+    /// Archives with the header at the end, as written by 7-Zip, are spooled into memory
+    /// or a temporary file first, see [ReaderOptions::spool_memory_limit].
+    /// Archives with the header first, see
+    /// [SevenZWriter::set_header_first](crate::SevenZWriter::set_header_first),
+    /// are extracted while they are received, so their entries can be read only once
+    /// and in archive order, e.g. with [for_each_entries](Self::for_each_entries).
+    /// If they have blocks with several packed streams, like BCJ2, which are read in turn,
+    /// the stream after the header is spooled too.
+    /// # Examples
+    /// ```no_run
+    /// use sevenz_rust::*;
+    /// let mut sz = SevenZReader::from_stream(std::io::stdin(), Password::empty()).unwrap();
+    /// sz.for_each_entries(|entry, _| {
+    ///     println!("{}", entry.name());
+    ///     Ok(true)
+    /// })
+    /// .unwrap();
+    /// ```
+    pub fn from_stream(reader: R, password: Password) -> Result<Self, Error> {
+        Self::from_stream_with_options(reader, password, ReaderOptions::default())
+    }
+
+    /// Returns the spooled stream, or `None` if the archive is read while it is received
+    /// because its header comes first. The stream after the header of such an archive
+    /// may be spooled too, see [from_stream](Self::from_stream).
+    pub fn spooled(&self) -> Option<&SpooledInput> {
+        match &self.source.input {
+            Input::Forward(_) => None,
+            Input::Spooled(spooled, _) => Some(spooled),
+        }
+    }
+
+    /// Like [from_stream](Self::from_stream), with options to limit the resources used for
+    /// untrusted archives.
+    pub fn from_stream_with_options(
+        mut reader: R,
+        password: Password,
+        options: ReaderOptions,
+    ) -> Result<Self, Error> {
+        let mut header = Vec::with_capacity(SIGNATURE_HEADER_SIZE as usize);
+        (&mut reader)
+            .take(SIGNATURE_HEADER_SIZE)
+            .read_to_end(&mut header)
+            .map_err(Error::io)?;
+        let stream = Cursor::new(header).chain(reader);
+        if is_header_first(stream.get_ref().0.get_ref()) {
+            let input = StreamInput {
+                input: Input::Forward(ForwardReader {
+                    inner: stream,
+                    pos: 0,
+                }),
+            };
+            // the signature is at the start, searching it would seek back
+            let options = options.with_signature_search_limit(0);
+            let mut seven = Self::new_with_options(input, u64::MAX, password, options)?;
+            let folders = &seven.archive().folders;
+            if folders.iter().any(|folder| folder.packed_streams.len() > 1) {
+                seven
+                    .source
+                    .spool_rest(options.spool_memory_limit)
+                    .map_err(Error::io)?;
+            }
+            Ok(seven)
+        } else {
+            let spooled =
+                SpooledInput::new(stream, options.spool_memory_limit).map_err(Error::io)?;
+            let len = spooled.len();
+            let input = StreamInput {
+                input: Input::Spooled(spooled, 0),
+            };
+            Self::new_with_options(input, len, password, options)
+        }
+    }
+}
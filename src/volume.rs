@@ -0,0 +1,272 @@
+//! Multi-volume archives split into files like 7-Zip does:
+//! `archive.7z.001`, `archive.7z.002`, ...
+use std::{
+    fs::File,
+    io::{ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, Password, SevenZReader};
+
+/// Returns the path of the volume with the 0-based `index`, e.g. `archive.7z.003` for 2.
+pub fn volume_path(base: impl AsRef<Path>, index: usize) -> PathBuf {
+    let mut path = base.as_ref().as_os_str().to_owned();
+    path.push(format!(".{:03}", index + 1));
+    PathBuf::from(path)
+}
+
+/// Strips the `.001` extension of the first volume, if present.
+fn base_path(path: &Path) -> PathBuf {
+    match path.extension() {
+        Some(ext) if ext == "001" => path.with_extension(""),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Presents the volumes of a split archive as one [Read] + [Seek] source,
+/// which can be passed to [Archive::read](crate::Archive::read) or [SevenZReader::new].
+/// # Examples
+/// ```no_run
+/// use sevenz_rust::*;
+/// let volumes = MultiVolumeReader::open("archive.7z.001").unwrap();
+/// let len = volumes.len();
+/// let mut sz = SevenZReader::new(volumes, len, Password::empty()).unwrap();
+/// ```
+pub struct MultiVolumeReader<R> {
+    volumes: Vec<R>,
+    /// Start position of each volume, followed by the total length.
+    offsets: Vec<u64>,
+    pos: u64,
+    /// Index of the volume whose cursor is at `pos`.
+    current: Option<usize>,
+}
+
+impl MultiVolumeReader<File> {
+    /// Opens all existing volumes of the archive at `path`,
+    /// which is either the first volume `archive.7z.001` or `archive.7z`.
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let base = base_path(path.as_ref());
+        let mut volumes = Vec::new();
+        loop {
+            let path = volume_path(&base, volumes.len());
+            match File::open(&path) {
+                Ok(file) => volumes.push(file),
+                Err(e) if e.kind() == ErrorKind::NotFound && !volumes.is_empty() => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Self::new(volumes)
+    }
+}
+
+impl<R: Read + Seek> MultiVolumeReader<R> {
+    /// Joins `volumes` in order, measuring the length of each.
+    pub fn new(mut volumes: Vec<R>) -> std::io::Result<Self> {
+        let mut offsets = Vec::with_capacity(volumes.len() + 1);
+        let mut offset = 0;
+        for volume in volumes.iter_mut() {
+            offsets.push(offset);
+            offset += volume.seek(SeekFrom::End(0))?;
+        }
+        offsets.push(offset);
+        Ok(Self {
+            volumes,
+            offsets,
+            pos: 0,
+            current: None,
+        })
+    }
+
+    /// Returns the total length of all volumes.
+    pub fn len(&self) -> u64 {
+        self.offsets[self.volumes.len()]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn into_inner(self) -> Vec<R> {
+        self.volumes
+    }
+}
+
+impl<R: Read + Seek> Read for MultiVolumeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos >= self.len() || buf.is_empty() {
+            return Ok(0);
+        }
+        let index = self.offsets.partition_point(|offset| *offset <= self.pos) - 1;
+        let volume = &mut self.volumes[index];
+        if self.current != Some(index) {
+            volume.seek(SeekFrom::Start(self.pos - self.offsets[index]))?;
+            self.current = Some(index);
+        }
+        let len = (buf.len() as u64).min(self.offsets[index + 1] - self.pos) as usize;
+        let size = volume.read(&mut buf[..len])?;
+        if size == 0 {
+            return Err(std::io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "volume is shorter than when it was opened",
+            ));
+        }
+        self.pos += size as u64;
+        if self.pos == self.offsets[index + 1] {
+            self.current = None;
+        }
+        Ok(size)
+    }
+}
+
+impl<R: Read + Seek> Seek for MultiVolumeReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.pos = seek_pos(self.pos, self.len(), pos)?;
+        self.current = None;
+        Ok(self.pos)
+    }
+}
+
+fn seek_pos(current: u64, len: u64, pos: SeekFrom) -> std::io::Result<u64> {
+    let new_pos = match pos {
+        SeekFrom::Start(pos) => Some(pos),
+        SeekFrom::End(pos) => len.checked_add_signed(pos),
+        SeekFrom::Current(pos) => current.checked_add_signed(pos),
+    };
+    new_pos.ok_or_else(|| std::io::Error::new(ErrorKind::InvalidInput, "SeekBeforeStart"))
+}
+
+/// Writes one [Write] + [Seek] stream into volume files of at most `volume_size` bytes,
+/// starting a new volume whenever the current one is full.
+///
+/// Seeking back, e.g. when [SevenZWriter::finish](crate::SevenZWriter::finish) writes
+/// the start header, reopens the earlier volume.
+pub struct MultiVolumeWriter {
+    base: PathBuf,
+    volume_size: u64,
+    /// The open volume with its index.
+    current: Option<(usize, File)>,
+    /// Number of volumes created so far.
+    created: usize,
+    pos: u64,
+    len: u64,
+    /// Whether the cursor of the open volume is not at `pos`.
+    moved: bool,
+}
+
+impl MultiVolumeWriter {
+    /// Creates the volumes `base.001`, `base.002`, ... of at most `volume_size` bytes each.
+    /// Existing volumes of an earlier archive at `base` are removed.
+    pub fn create(base: impl AsRef<Path>, volume_size: u64) -> std::io::Result<Self> {
+        if volume_size == 0 {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "volume size must not be 0",
+            ));
+        }
+        let base = base.as_ref().to_path_buf();
+        let mut index = 0;
+        while volume_path(&base, index).exists() {
+            std::fs::remove_file(volume_path(&base, index))?;
+            index += 1;
+        }
+        Ok(Self {
+            base,
+            volume_size,
+            current: None,
+            created: 0,
+            pos: 0,
+            len: 0,
+            moved: false,
+        })
+    }
+
+    /// Returns the number of volumes written so far.
+    pub fn volume_count(&self) -> usize {
+        self.created
+    }
+
+    /// Makes the volume with `index` the open one, creating the volumes up to it.
+    fn open_volume(&mut self, index: usize) -> std::io::Result<()> {
+        if !matches!(self.current, Some((i, _)) if i == index) {
+            self.current = None;
+            while self.created < index {
+                File::create(volume_path(&self.base, self.created))?;
+                self.created += 1;
+            }
+            let path = volume_path(&self.base, index);
+            let file = if index == self.created {
+                self.created += 1;
+                File::create(path)?
+            } else {
+                std::fs::OpenOptions::new().write(true).open(path)?
+            };
+            self.current = Some((index, file));
+            self.moved = true;
+        }
+        Ok(())
+    }
+}
+
+impl Write for MultiVolumeWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let index = (self.pos / self.volume_size) as usize;
+        let offset = self.pos % self.volume_size;
+        let len = (buf.len() as u64).min(self.volume_size - offset) as usize;
+        self.open_volume(index)?;
+        let volume = &mut self.current.as_mut().unwrap().1;
+        if self.moved {
+            volume.seek(SeekFrom::Start(offset))?;
+        }
+        let size = volume.write(&buf[..len])?;
+        self.moved = false;
+        self.pos += size as u64;
+        self.len = self.len.max(self.pos);
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.current {
+            Some((_, file)) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Seek for MultiVolumeWriter {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.pos = seek_pos(self.pos, self.len, pos)?;
+        self.moved = true;
+        Ok(self.pos)
+    }
+}
+
+impl SevenZReader<MultiVolumeReader<File>> {
+    /// Opens a split archive, see [MultiVolumeReader::open].
+    pub fn open_volumes(path: impl AsRef<Path>, password: Password) -> Result<Self, Error> {
+        let volumes = MultiVolumeReader::open(path.as_ref())
+            .map_err(|e| Error::file_open(e, path.as_ref().to_string_lossy().to_string()))?;
+        let len = volumes.len();
+        Self::new(volumes, len, password)
+    }
+}
+
+#[cfg(feature = "compress")]
+impl crate::SevenZWriter<MultiVolumeWriter> {
+    /// Creates a split archive with volumes of at most `volume_size` bytes,
+    /// see [MultiVolumeWriter::create].
+    /// # Examples
+    /// ```no_run
+    /// use sevenz_rust::*;
+    /// let mut sz = SevenZWriter::create_volumes("archive.7z", 100 * 1024 * 1024).unwrap();
+    /// sz.push_source_path("path/to/dir", |_| true).unwrap();
+    /// sz.finish().unwrap();
+    /// ```
+    pub fn create_volumes(base: impl AsRef<Path>, volume_size: u64) -> Result<Self, Error> {
+        let writer = MultiVolumeWriter::create(base.as_ref(), volume_size)
+            .map_err(|e| Error::file_open(e, base.as_ref().to_string_lossy().to_string()))?;
+        Self::new(writer)
+    }
+}
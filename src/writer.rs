@@ -1,21 +1,40 @@
+mod editor;
 mod pack_info;
 mod seq_reader;
+mod spool;
 mod unpack_info;
 
-use crate::{archive::*, encoders, lzma::*, reader::CRC32, Error, SevenZArchiveEntry};
+use crate::{
+    archive::*,
+    encoders,
+    folder::Folder,
+    lzma::*,
+    progress::{is_cancelled, PackedCountingWriter, ProgressReader},
+    reader::CRC32,
+    CancellationToken, Error, Password, Progress, ReaderOptions, SevenZArchiveEntry,
+};
 use bit_set::BitSet;
 use byteorder::*;
 use std::{
     cell::Cell,
+    collections::BTreeMap,
     fs::File,
-    io::{Read, Seek, Write},
+    io::{Read, Seek, SeekFrom, Write},
     path::Path,
     rc::Rc,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
 };
 
+pub use self::editor::*;
 pub use self::seq_reader::*;
-use self::{pack_info::PackInfo, unpack_info::UnpackInfo};
+pub use self::spool::*;
+use self::{
+    pack_info::PackInfo,
+    unpack_info::{FolderInfo, UnpackInfo},
+};
 
 macro_rules! write_times {
     //write_i64
@@ -71,6 +90,14 @@ pub struct SevenZWriter<W: Write> {
     pack_info: PackInfo,
     unpack_info: UnpackInfo,
     encrypt_header: bool,
+    num_threads: usize,
+    pub(crate) follow_symlinks: bool,
+    progress: Option<Box<dyn Progress + Send>>,
+    cancellation_token: Option<CancellationToken>,
+    /// Position of the signature header in `output`, after the stub if there is one.
+    signature_offset: u64,
+    /// Cuts `output` to a length, for the outputs that can shrink like files.
+    truncate: Option<fn(&mut W, u64) -> std::io::Result<()>>,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -79,15 +106,125 @@ impl SevenZWriter<File> {
     pub fn create(path: impl AsRef<Path>) -> Result<Self> {
         let file = std::fs::File::create(path.as_ref())
             .map_err(|e| crate::Error::file_open(e, path.as_ref().to_string_lossy().to_string()))?;
-        Self::new(file)
+        let mut sz = Self::new(file)?;
+        sz.truncate = Some(|file, len| file.set_len(len));
+        Ok(sz)
+    }
+}
+impl<W: Read + Write + Seek> SevenZWriter<W> {
+    /// Opens the existing 7z archive in `writer` to append entries to it.
+    ///
+    /// The packed streams of the existing entries are kept untouched, new entries are
+    /// written after them and [finish](Self::finish) writes a header listing all entries.
+    ///
+    /// The old header is overwritten by the new data, so the archive is only valid again
+    /// once `finish` returns. If the result is shorter than the original archive, truncate
+    /// the returned writer to its current position (e.g. `File::set_len`).
+    /// # Examples
+    /// ```no_run
+    /// use sevenz_rust::*;
+    /// use std::io::Seek;
+    /// let file = std::fs::OpenOptions::new().read(true).write(true).open("path/to/dest.7z").unwrap();
+    /// let mut sz = SevenZWriter::open_for_append(file).expect("open ok");
+    /// sz.push_archive_entry(
+    ///     SevenZArchiveEntry::from_path("path/to/new.txt", "new.txt".into()),
+    ///     Some(std::fs::File::open("path/to/new.txt").unwrap()),
+    /// )
+    /// .expect("ok");
+    /// let mut file = sz.finish().expect("done");
+    /// let len = file.stream_position().unwrap();
+    /// file.set_len(len).unwrap();
+    /// ```
+    #[inline]
+    pub fn open_for_append(writer: W) -> Result<Self> {
+        Self::open_for_append_with_options(writer, Password::empty(), ReaderOptions::default())
+    }
+
+    /// Like [open_for_append](Self::open_for_append), with the `password` of an archive
+    /// with an encrypted header and `options` to read it, e.g.
+    /// [ReaderOptions::signature_search_limit](crate::ReaderOptions::signature_search_limit)
+    /// for an archive after a stub, which is kept in front of it.
+    ///
+    /// The new header is only encrypted if the content methods set with
+    /// [set_content_methods](Self::set_content_methods) include AES.
+    pub fn open_for_append_with_options(
+        mut writer: W,
+        password: Password,
+        options: ReaderOptions,
+    ) -> Result<Self> {
+        let len = writer.seek(SeekFrom::End(0)).map_err(Error::io)?;
+        writer.seek(SeekFrom::Start(0)).map_err(Error::io)?;
+        let archive = Archive::read_with_options(&mut writer, len, password.as_slice(), options)?;
+        let pack_end = archive.signature_offset
+            + SIGNATURE_HEADER_SIZE
+            + archive.pack_pos
+            + archive.pack_sizes.iter().sum::<u64>();
+
+        let mut sz = Self::new_at(writer, archive.signature_offset)?;
+        sz.output
+            .seek(SeekFrom::Start(pack_end))
+            .map_err(Error::io)?;
+        sz.pack_info.pos = archive.pack_pos;
+        for (i, size) in archive.pack_sizes.iter().copied().enumerate() {
+            let crc = if archive.pack_crcs_defined.contains(i) {
+                archive.pack_crcs[i] as u32
+            } else {
+                0
+            };
+            sz.pack_info.add_stream(size, crc);
+        }
+        let Archive {
+            folders,
+            files,
+            stream_map,
+            ..
+        } = archive;
+        for (folder_index, folder) in folders.into_iter().enumerate() {
+            let start = stream_map.folder_first_file_index[folder_index];
+            let (sizes, crcs) = files[start..]
+                .iter()
+                .filter(|f| f.has_stream)
+                .take(folder.num_unpack_sub_streams)
+                .map(|f| (f.size, f.has_crc.then_some(f.crc as u32)))
+                .unzip();
+            sz.unpack_info.add_folder(folder, sizes, crcs);
+        }
+        sz.files = files;
+        Ok(sz)
     }
 }
+
 impl<W: Write + Seek> SevenZWriter<W> {
     /// Prepares writer to write a 7z archive to
-    pub fn new(mut writer: W) -> Result<Self> {
+    pub fn new(writer: W) -> Result<Self> {
+        Self::new_at(writer, 0)
+    }
+
+    /// Prepares writer to write a 7z archive after `stub`, e.g. the executable of
+    /// a self-extracting archive. The archive can be read with
+    /// [ReaderOptions::signature_search_limit](crate::ReaderOptions::signature_search_limit)
+    /// set to at least the length of the stub.
+    /// # Examples
+    /// ```no_run
+    /// use sevenz_rust::*;
+    /// let stub = std::fs::read("7z.sfx").unwrap();
+    /// let file = std::fs::File::create("dest.exe").unwrap();
+    /// let mut sz = SevenZWriter::new_with_stub(file, &stub).unwrap();
+    /// sz.push_source_path("path/to/compress", |_| true).unwrap();
+    /// sz.finish().unwrap();
+    /// ```
+    pub fn new_with_stub(mut writer: W, stub: &[u8]) -> Result<Self> {
+        writer
+            .seek(std::io::SeekFrom::Start(0))
+            .map_err(Error::io)?;
+        writer.write_all(stub).map_err(Error::io)?;
+        Self::new_at(writer, stub.len() as u64)
+    }
+
+    fn new_at(mut writer: W, signature_offset: u64) -> Result<Self> {
         writer
             .seek(std::io::SeekFrom::Start(
-                crate::archive::SIGNATURE_HEADER_SIZE,
+                signature_offset + crate::archive::SIGNATURE_HEADER_SIZE,
             ))
             .map_err(Error::io)?;
 
@@ -98,12 +235,25 @@ impl<W: Write + Seek> SevenZWriter<W> {
             pack_info: Default::default(),
             unpack_info: Default::default(),
             encrypt_header: true,
+            num_threads: 1,
+            follow_symlinks: false,
+            progress: None,
+            cancellation_token: None,
+            signature_offset,
+            truncate: None,
         })
     }
 
     /// Sets the default compression methods to use for entry contents.
     /// The default is LZMA2.
-    /// And currently only support LZMA2
+    ///
+    /// The first method writes the packed stream and each following method feeds the one before it,
+    /// so encryption comes first, e.g. `vec![AES256SHA256, LZMA2]`.
+    /// Filters are applied to the data before it is compressed wherever they are in the list,
+    /// e.g. `vec![BCJ_X86_FILTER, LZMA2]`.
+    ///
+    /// The methods other than `BCJ2_FILTER` compress the main stream of BCJ2,
+    /// its call and jump streams are compressed with LZMA.
     ///
     pub fn set_content_methods(
         &mut self,
@@ -122,6 +272,45 @@ impl<W: Write + Seek> SevenZWriter<W> {
         self.encrypt_header = enabled;
     }
 
+    /// Sets the number of threads used to compress entries that each go into their own block,
+    /// see [push_archive_entries_non_solid](Self::push_archive_entries_non_solid).
+    /// The default is 1, which compresses on the calling thread.
+    pub fn set_num_threads(&mut self, num_threads: usize) -> &mut Self {
+        self.num_threads = num_threads.max(1);
+        self
+    }
+
+    /// Sets whether the path helpers like [push_source_path](Self::push_source_path) compress
+    /// the files that symbolic links point to instead of the links.
+    /// The default is `false`, which stores each link with its target as content, like p7zip.
+    /// A followed link to a directory that contains it is skipped instead of walked forever.
+    pub fn set_follow_symlinks(&mut self, follow: bool) -> &mut Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Sets the receiver of the progress of adding entries.
+    /// Entries compressed concurrently by
+    /// [push_archive_entries_non_solid](Self::push_archive_entries_non_solid) are reported
+    /// once each when its compressed data is written to the output.
+    pub fn set_progress(&mut self, progress: impl Progress + Send + 'static) -> &mut Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Sets the token to stop adding entries with [Error::Cancelled].
+    /// The entry being added when cancelled, or the whole solid block
+    /// of [push_archive_entries](Self::push_archive_entries), is left out,
+    /// so the archive can still be [finished](Self::finish) with the entries before it.
+    ///
+    /// The partially written data is cut off the outputs of [create](SevenZWriter::create)
+    /// and [new_streaming](SevenZWriter::new_streaming), other outputs may keep it
+    /// after the end of the archive.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) -> &mut Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
     /// Create an archive entry using the file in `path` and entry_name provided.
     /// #deprecated use SevenZArchiveEntry::from_path instead
     #[deprecated]
@@ -165,78 +354,268 @@ impl<W: Write + Seek> SevenZWriter<W> {
     /// ```
     pub fn push_archive_entry<R: Read>(
         &mut self,
-        mut entry: SevenZArchiveEntry,
+        entry: SevenZArchiveEntry,
         reader: Option<R>,
     ) -> Result<&SevenZArchiveEntry> {
+        if is_cancelled(self.cancellation_token.as_ref()) {
+            return Err(Error::Cancelled);
+        }
+        if let Some(progress) = &mut self.progress {
+            progress.entry_started(&entry);
+        }
         if !entry.is_directory {
-            if let Some(mut r) = reader {
-                let mut compressed_len = 0;
-                let mut compressed = CompressWrapWriter::new(&mut self.output, &mut compressed_len);
+            if let Some(r) = reader {
+                let start = self.output.stream_position().map_err(Error::io)?;
                 let content_methods = if entry.content_methods.is_empty() {
                     &self.content_methods
                 } else {
                     &entry.content_methods
                 };
-                let mut more_sizes: Vec<Rc<Cell<usize>>> =
-                    Vec::with_capacity(content_methods.len() - 1);
-
-                let (crc, size) = {
-                    let mut w =
-                        Self::create_writer(content_methods, &mut compressed, &mut more_sizes)?;
-                    let mut write_len = 0;
-                    let mut w = CompressWrapWriter::new(&mut w, &mut write_len);
-                    let mut buf = [0u8; 4096];
-                    loop {
-                        match r.read(&mut buf) {
-                            Ok(n) => {
-                                if n == 0 {
-                                    break;
-                                }
-                                w.write_all(&buf[..n]).map_err(|e| {
-                                    Error::io_msg(e, format!("Encode entry:{}", entry.name()))
+                let packed = Cell::new(0);
+                let progress = self.progress.as_deref_mut().map(|p| p as &mut dyn Progress);
+                let token = self.cancellation_token.as_ref();
+                let mut r = ProgressReader::new(r, progress, token, &packed);
+                let result = Self::encode_pack_stream(
+                    PackedCountingWriter::new(&mut self.output, &packed),
+                    content_methods,
+                    || format!("Encode entry:{}", entry.name()),
+                    |w| std::io::copy(&mut r, w).map(|_| ()),
+                );
+                r.report(0);
+                let stream = result.map_err(|e| self.cancelled_or(e, start))?;
+                self.push_encoded_entry(entry, stream);
+                return Ok(self.entry_finished());
+            }
+        }
+        self.push_empty_entry(entry);
+        Ok(self.entry_finished())
+    }
+
+    /// Reports the last added entry as finished and returns it.
+    fn entry_finished(&mut self) -> &SevenZArchiveEntry {
+        let entry = self.files.last().unwrap();
+        if let Some(progress) = &mut self.progress {
+            progress.entry_finished(entry);
+        }
+        entry
+    }
+
+    /// Returns [Error::Cancelled] instead of `e` if the token was cancelled,
+    /// moving the output back to `start` and cutting it there if it can,
+    /// to leave out the partially written data.
+    fn cancelled_or(&mut self, e: Error, start: u64) -> Error {
+        if !is_cancelled(self.cancellation_token.as_ref()) {
+            return e;
+        }
+        let result = self
+            .output
+            .seek(SeekFrom::Start(start))
+            .and_then(|_| match self.truncate {
+                Some(truncate) => truncate(&mut self.output, start),
+                None => Ok(()),
+            });
+        match result {
+            Ok(()) => Error::Cancelled,
+            Err(e) => Error::io(e),
+        }
+    }
+
+    /// Adds `entries` each compressed into its own block,
+    /// like calling [push_archive_entry](Self::push_archive_entry) for each of them.
+    ///
+    /// With more than one thread set by [set_num_threads](Self::set_num_threads), entries are
+    /// compressed concurrently into memory buffers which are then written to the output
+    /// in the order of `entries`, so the archive is the same as with a single thread.
+    /// At most twice the number of threads entries are compressed ahead of the next one
+    /// to write, which bounds the memory used by the buffers waiting to be written.
+    /// On error, the entries before the failed one have been added.
+    pub fn push_archive_entries_non_solid<R: Read + Send>(
+        &mut self,
+        entries: Vec<(SevenZArchiveEntry, Option<R>)>,
+    ) -> Result<&mut Self> {
+        let num_threads = self.num_threads.min(entries.len());
+        if num_threads <= 1 {
+            for (entry, reader) in entries {
+                self.push_archive_entry(entry, reader)?;
+            }
+            return Ok(self);
+        }
+
+        let default_methods = self.content_methods.clone();
+        let token = self.cancellation_token.clone();
+        let total = entries.len();
+        let jobs = Mutex::new(entries.into_iter().enumerate());
+        // a job failed, the jobs after it are not started, those already taken are still sent
+        let failed = AtomicBool::new(false);
+        // the results are not written anymore
+        let stopped = AtomicBool::new(false);
+        // index of the next entry to write, workers wait for it to come within `window`
+        let window = num_threads * 2;
+        let written = (Mutex::new(0usize), Condvar::new());
+        let stop = || {
+            let _next = written.0.lock().unwrap();
+            failed.store(true, Ordering::Relaxed);
+            stopped.store(true, Ordering::Relaxed);
+            written.1.notify_all();
+        };
+        let (tx, rx) = std::sync::mpsc::sync_channel(num_threads);
+        std::thread::scope(|scope| {
+            for _ in 0..num_threads {
+                let tx = tx.clone();
+                let (jobs, failed, stopped, written, default_methods, token) = (
+                    &jobs,
+                    &failed,
+                    &stopped,
+                    &written,
+                    &default_methods,
+                    token.as_ref(),
+                );
+                scope.spawn(move || {
+                    while !failed.load(Ordering::Relaxed) {
+                        let Some((i, (entry, reader))) = jobs.lock().unwrap().next() else {
+                            break;
+                        };
+                        let (next, ready) = written;
+                        drop(
+                            ready
+                                .wait_while(next.lock().unwrap(), |next| {
+                                    i >= *next + window && !stopped.load(Ordering::Relaxed)
+                                })
+                                .unwrap(),
+                        );
+                        if stopped.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        let result =
+                            Self::encode_entry_to_buf(entry, reader, default_methods, token);
+                        if result.is_err() {
+                            failed.store(true, Ordering::Relaxed);
+                        }
+                        if tx.send((i, result)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(tx);
+
+            // results arrive in any order, write them in the order of `entries`
+            let mut done = BTreeMap::new();
+            let mut next = 0;
+            for (i, result) in rx {
+                done.insert(i, result);
+                while let Some(result) = done.remove(&next) {
+                    next += 1;
+                    *written.0.lock().unwrap() = next;
+                    written.1.notify_all();
+                    if is_cancelled(token.as_ref()) {
+                        stop();
+                        return Err(Error::Cancelled);
+                    }
+                    let result = result.and_then(|(entry, encoded)| {
+                        if let Some(progress) = &mut self.progress {
+                            progress.entry_started(&entry);
+                        }
+                        match encoded {
+                            Some((stream, buf)) => {
+                                self.output.write_all(&buf).map_err(|e| {
+                                    Error::io_msg(e, format!("Write entry:{}", entry.name()))
                                 })?;
+                                if let Some(progress) = &mut self.progress {
+                                    progress.bytes_processed(stream.size, buf.len() as u64);
+                                }
+                                self.push_encoded_entry(entry, stream);
                             }
-                            Err(e) => {
-                                return Err(Error::io_msg(
-                                    e,
-                                    format!("Encode entry:{}", entry.name()),
-                                ));
+                            None => {
+                                self.push_empty_entry(entry);
                             }
                         }
+                        self.entry_finished();
+                        Ok(())
+                    });
+                    if let Err(e) = result {
+                        stop();
+                        return Err(e);
                     }
-                    w.flush()
-                        .map_err(|e| Error::io_msg(e, format!("Encode entry:{}", entry.name())))?;
-                    w.write(&[])
-                        .map_err(|e| Error::io_msg(e, format!("Encode entry:{}", entry.name())))?;
+                }
+            }
+            if let Some(e) = done.into_values().find_map(|result| result.err()) {
+                return Err(e);
+            }
+            if next < total {
+                return Err(Error::other(format!(
+                    "only {} of {} entries were compressed",
+                    next, total
+                )));
+            }
+            Ok(())
+        })?;
+        Ok(self)
+    }
 
-                    (w.crc_value(), write_len)
-                };
-                let compressed_crc = compressed.crc_value();
-                entry.has_stream = true;
-                entry.size = size as u64;
-                entry.crc = crc as u64;
-                entry.has_crc = true;
-                entry.compressed_crc = compressed_crc as u64;
-                entry.compressed_size = compressed_len as u64;
-                self.pack_info
-                    .add_stream(compressed_len as u64, compressed_crc);
-
-                let mut sizes = Vec::with_capacity(more_sizes.len() + 1);
-                sizes.extend(more_sizes.iter().map(|s| s.get() as u64));
-                sizes.push(size as u64);
-
-                self.unpack_info.add(content_methods.clone(), sizes, crc);
-
-                self.files.push(entry);
-                return Ok(self.files.last().unwrap());
+    /// Compresses the content of `entry` into a new buffer, returns `None` if it has no content.
+    #[allow(clippy::type_complexity)]
+    fn encode_entry_to_buf<R: Read>(
+        entry: SevenZArchiveEntry,
+        reader: Option<R>,
+        default_methods: &[SevenZMethodConfiguration],
+        token: Option<&CancellationToken>,
+    ) -> Result<(SevenZArchiveEntry, Option<(PackStream, Vec<u8>)>)> {
+        let r = match reader {
+            Some(r) if !entry.is_directory => r,
+            _ => return Ok((entry, None)),
+        };
+        let content_methods = if entry.content_methods.is_empty() {
+            default_methods
+        } else {
+            &entry.content_methods
+        };
+        let mut buf = Vec::new();
+        // the progress is reported when the buffer is written to the output
+        let packed = Cell::new(0);
+        let mut r = ProgressReader::new(r, None, token, &packed);
+        let stream = Self::encode_pack_stream(
+            &mut buf,
+            content_methods,
+            || format!("Encode entry:{}", entry.name()),
+            |w| std::io::copy(&mut r, w).map(|_| ()),
+        )
+        .map_err(|e| {
+            if r.is_cancelled() {
+                Error::Cancelled
+            } else {
+                e
             }
-        }
+        })?;
+        Ok((entry, Some((stream, buf))))
+    }
+
+    fn push_encoded_entry(
+        &mut self,
+        mut entry: SevenZArchiveEntry,
+        stream: PackStream,
+    ) -> &SevenZArchiveEntry {
+        entry.has_stream = true;
+        entry.size = stream.size;
+        entry.crc = stream.crc as u64;
+        entry.has_crc = true;
+        entry.compressed_crc = stream.pack_crcs[0] as u64;
+        entry.compressed_size = stream.pack_sizes.iter().sum();
+        let sizes = vec![stream.size];
+        let crcs = vec![Some(stream.crc)];
+        self.add_pack_stream(stream, sizes, crcs);
+
+        self.files.push(entry);
+        self.files.last().unwrap()
+    }
+
+    fn push_empty_entry(&mut self, mut entry: SevenZArchiveEntry) -> &SevenZArchiveEntry {
         entry.has_stream = false;
         entry.size = 0;
         entry.compressed_size = 0;
         entry.has_crc = false;
         self.files.push(entry);
-        Ok(self.files.last().unwrap())
+        self.files.last().unwrap()
     }
 
     /// [Solid compression](https://en.wikipedia.org/wiki/Solid_compression)
@@ -248,65 +627,40 @@ impl<W: Write + Seek> SevenZWriter<W> {
         mut entries: Vec<SevenZArchiveEntry>,
         reader: SeqReader<SourceReader<R>>,
     ) -> Result<&mut Self> {
-        let mut r = reader;
-        assert_eq!(r.reader_len(), entries.len());
-        let mut compressed_len = 0;
-        let mut compressed = CompressWrapWriter::new(&mut self.output, &mut compressed_len);
+        assert_eq!(reader.reader_len(), entries.len());
+        if is_cancelled(self.cancellation_token.as_ref()) {
+            return Err(Error::Cancelled);
+        }
+        let start = self.output.stream_position().map_err(Error::io)?;
         let content_methods = &self.content_methods;
-        let mut more_sizes: Vec<Rc<Cell<usize>>> = Vec::with_capacity(content_methods.len() - 1);
-
-        let (crc, size) = {
-            let mut w = Self::create_writer(content_methods, &mut compressed, &mut more_sizes)?;
-            let mut write_len = 0;
-            let mut w = CompressWrapWriter::new(&mut w, &mut write_len);
-            let mut buf = [0u8; 4096];
-            fn entries_names(entries: &[SevenZArchiveEntry]) -> String {
-                let mut names = String::with_capacity(512);
-                for ele in entries.iter() {
-                    names.push_str(&ele.name);
-                    names.push(';');
-                    if names.len() > 512 {
-                        break;
-                    }
-                }
-                names
-            }
-            loop {
-                match r.read(&mut buf) {
-                    Ok(n) => {
-                        if n == 0 {
-                            break;
-                        }
-                        w.write_all(&buf[..n]).map_err(|e| {
-                            Error::io_msg(e, format!("Encode entries:{}", entries_names(&entries)))
-                        })?;
+        let packed = Cell::new(0);
+        let progress = self.progress.as_deref_mut().map(|p| p as &mut dyn Progress);
+        let token = self.cancellation_token.as_ref();
+        let mut r = ProgressReader::new(reader, progress, token, &packed);
+        let result = Self::encode_pack_stream(
+            PackedCountingWriter::new(&mut self.output, &packed),
+            content_methods,
+            || format!("Encode entries:{}", entries_names(&entries)),
+            |w| {
+                let mut buf = vec![0; 64 * 1024];
+                let mut started = 0;
+                loop {
+                    // an entry is reported as started once the reader before it is exhausted
+                    while started < entries.len() && started <= r.inner.current() {
+                        r.entry_started(&entries[started]);
+                        started += 1;
                     }
-                    Err(e) => {
-                        return Err(Error::io_msg(
-                            e,
-                            format!("Encode entries:{}", entries_names(&entries)),
-                        ));
-                    }
-                }
-            }
-            w.flush().map_err(|e| {
-                let mut names = String::with_capacity(512);
-                for ele in entries.iter() {
-                    names.push_str(&ele.name);
-                    names.push(';');
-                    if names.len() > 512 {
-                        break;
+                    let size = r.read(&mut buf)?;
+                    if size == 0 {
+                        return Ok(());
                     }
+                    w.write_all(&buf[..size])?;
                 }
-                Error::io_msg(e, format!("Encode entry:{}", names))
-            })?;
-            w.write(&[]).map_err(|e| {
-                Error::io_msg(e, format!("Encode entry:{}", entries_names(&entries)))
-            })?;
-
-            (w.crc_value(), write_len)
-        };
-        let compressed_crc = compressed.crc_value();
+            },
+        );
+        r.report(0);
+        let r = r.inner;
+        let stream = result.map_err(|e| self.cancelled_or(e, start))?;
         let mut sub_stream_crcs = Vec::with_capacity(entries.len());
         let mut sub_stream_sizes = Vec::with_capacity(entries.len());
         for i in 0..entries.len() {
@@ -319,24 +673,171 @@ impl<W: Write + Seek> SevenZWriter<W> {
             entry.has_crc = true;
         }
 
-        self.pack_info
-            .add_stream(compressed_len as u64, compressed_crc);
+        self.add_pack_stream(
+            stream,
+            sub_stream_sizes,
+            sub_stream_crcs.into_iter().map(Some).collect(),
+        );
+
+        if let Some(progress) = &mut self.progress {
+            for entry in &entries {
+                progress.entry_finished(entry);
+            }
+        }
+        self.files.extend(entries);
+        Ok(self)
+    }
+
+    /// Encodes the data written by `write_data` with `methods` into `out` as one folder.
+    /// `describe` names the data in error messages.
+    fn encode_pack_stream<O: Write>(
+        out: O,
+        methods: &[SevenZMethodConfiguration],
+        describe: impl Fn() -> String,
+        write_data: impl FnOnce(&mut dyn Write) -> std::io::Result<()>,
+    ) -> Result<PackStream> {
+        let methods = &filters_last(methods);
+        if let Some((last, main_methods)) = methods.split_last() {
+            if last.method == SevenZMethod::BCJ2_FILTER {
+                return Self::encode_bcj2_pack_stream(out, main_methods, describe, write_data);
+            }
+        }
+        let mut compressed_len = 0;
+        let mut compressed = CompressWrapWriter::new(out, &mut compressed_len);
+        let mut more_sizes: Vec<Rc<Cell<usize>>> = Vec::with_capacity(methods.len() - 1);
+
+        let (crc, size) = {
+            let mut w = Self::create_writer(methods, &mut compressed, &mut more_sizes)?;
+            let mut write_len = 0;
+            let mut w = CompressWrapWriter::new(&mut w, &mut write_len);
+            write_data(&mut w)
+                .and_then(|_| w.flush())
+                .and_then(|_| w.write(&[]))
+                .map_err(|e| Error::io_msg(e, describe()))?;
+
+            (w.crc_value(), write_len)
+        };
+        let compressed_crc = compressed.crc_value();
 
         let mut sizes = Vec::with_capacity(more_sizes.len() + 1);
         sizes.extend(more_sizes.iter().map(|s| s.get() as u64));
         sizes.push(size as u64);
-
-        self.unpack_info.add_multiple(
-            content_methods.clone(),
-            sizes,
+        Ok(PackStream {
             crc,
-            entries.len() as u64,
-            sub_stream_sizes,
-            sub_stream_crcs,
+            size: size as u64,
+            folder: FolderInfo::folder_of_methods(methods, sizes, crc),
+            pack_sizes: vec![compressed_len as u64],
+            pack_crcs: vec![compressed_crc],
+        })
+    }
+
+    /// Like [SevenZWriter::encode_pack_stream], but for a folder with a BCJ2 coder.
+    /// Only the main stream is written to `out` while encoding,
+    /// the other streams are small and kept in memory until it is done.
+    fn encode_bcj2_pack_stream<O: Write>(
+        mut out: O,
+        main_methods: &[SevenZMethodConfiguration],
+        describe: impl Fn() -> String,
+        write_data: impl FnOnce(&mut dyn Write) -> std::io::Result<()>,
+    ) -> Result<PackStream> {
+        if main_methods
+            .iter()
+            .any(|m| m.method == SevenZMethod::AES256SHA256)
+        {
+            return Err(Error::unsupported("BCJ2 can't be combined with encryption"));
+        }
+        let side_method = SevenZMethodConfiguration::new(SevenZMethod::LZMA).with_options(
+            LZMA2Options {
+                dict_size: 1 << 20,
+                lc: 0,
+                lp: 2,
+                ..Default::default()
+            }
+            .into(),
         );
+        let side_methods = std::slice::from_ref(&side_method);
 
-        self.files.extend(entries);
-        Ok(self)
+        let mut unpack_lens = [0usize; 4];
+        let mut pack_lens = [0usize; 4];
+        let mut side_bufs = [Vec::new(), Vec::new(), Vec::new()];
+        let mut more_sizes: Vec<Rc<Cell<usize>>> = Vec::with_capacity(main_methods.len());
+        let mut pack_crcs = Vec::with_capacity(4);
+        let (crc, size) = {
+            let [main_len, call_len, jump_len, rc_len] = &mut unpack_lens;
+            let [main_pack_len, call_pack_len, jump_pack_len, rc_pack_len] = &mut pack_lens;
+            let [call_buf, jump_buf, rc_buf] = &mut side_bufs;
+            let mut main_packed = CompressWrapWriter::new(&mut out, main_pack_len);
+            let mut call_packed = CompressWrapWriter::new(call_buf, call_pack_len);
+            let mut jump_packed = CompressWrapWriter::new(jump_buf, jump_pack_len);
+            let mut rc_packed = CompressWrapWriter::new(rc_buf, rc_pack_len);
+            let (crc, size) = {
+                let outputs: Vec<Box<dyn Write>> = vec![
+                    Box::new(CompressWrapWriter::new(
+                        Self::create_writer(main_methods, &mut main_packed, &mut more_sizes)?,
+                        main_len,
+                    )),
+                    Box::new(CompressWrapWriter::new(
+                        Self::create_writer(side_methods, &mut call_packed, &mut vec![])?,
+                        call_len,
+                    )),
+                    Box::new(CompressWrapWriter::new(
+                        Self::create_writer(side_methods, &mut jump_packed, &mut vec![])?,
+                        jump_len,
+                    )),
+                    Box::new(CompressWrapWriter::new(&mut rc_packed, rc_len)),
+                ];
+                let mut w = crate::bcj2::BCJ2Writer::new(outputs);
+                let mut write_len = 0;
+                let mut w = CompressWrapWriter::new(&mut w, &mut write_len);
+                write_data(&mut w)
+                    .and_then(|_| w.flush())
+                    .and_then(|_| w.write(&[]))
+                    .map_err(|e| Error::io_msg(e, describe()))?;
+                (w.crc_value(), write_len)
+            };
+            pack_crcs.extend([
+                main_packed.crc_value(),
+                call_packed.crc_value(),
+                jump_packed.crc_value(),
+                rc_packed.crc_value(),
+            ]);
+            (crc, size)
+        };
+        for buf in side_bufs.iter() {
+            out.write_all(buf)
+                .map_err(|e| Error::io_msg(e, describe()))?;
+        }
+
+        let mut sizes = Vec::with_capacity(more_sizes.len() + 4);
+        sizes.extend(more_sizes.iter().map(|s| s.get() as u64));
+        if !main_methods.is_empty() {
+            sizes.push(unpack_lens[0] as u64);
+        }
+        sizes.push(unpack_lens[1] as u64);
+        sizes.push(unpack_lens[2] as u64);
+        sizes.push(size as u64);
+        Ok(PackStream {
+            crc,
+            size: size as u64,
+            folder: FolderInfo::bcj2_folder_of_methods(main_methods, &side_method, sizes, crc),
+            pack_sizes: pack_lens.iter().map(|&l| l as u64).collect(),
+            pack_crcs,
+        })
+    }
+
+    /// Adds the folder and the packed streams of an encoded `stream` that holds entries of
+    /// `sub_stream_sizes`.
+    fn add_pack_stream(
+        &mut self,
+        stream: PackStream,
+        sub_stream_sizes: Vec<u64>,
+        sub_stream_crcs: Vec<Option<u32>>,
+    ) {
+        for (size, crc) in stream.pack_sizes.iter().zip(stream.pack_crcs.iter()) {
+            self.pack_info.add_stream(*size, *crc);
+        }
+        self.unpack_info
+            .add_folder(stream.folder, sub_stream_sizes, sub_stream_crcs);
     }
 
     fn create_writer<'a, O: Write + 'a>(
@@ -366,31 +867,52 @@ impl<W: Write + Seek> SevenZWriter<W> {
         self.write_encoded_header(&mut header)?;
         let header_pos = self.output.stream_position()?;
         self.output.write_all(&header)?;
-        let crc32 = CRC32.checksum(&header);
-        let mut hh = [0u8; SIGNATURE_HEADER_SIZE as usize];
-        {
-            let mut hhw = hh.as_mut_slice();
-            //sig
-            hhw.write_all(SEVEN_Z_SIGNATURE)?;
-            //version
-            hhw.write_u8(0)?;
-            hhw.write_u8(2)?;
-            //placeholder for crc: index = 8
-            hhw.write_u32::<LittleEndian>(0)?;
-
-            // start header
-            hhw.write_u64::<LittleEndian>(header_pos - SIGNATURE_HEADER_SIZE)?;
-            hhw.write_u64::<LittleEndian>(0xffffffff & header.len() as u64)?;
-            hhw.write_u32::<LittleEndian>(crc32)?;
-        }
-        let crc32 = CRC32.checksum(&hh[12..]);
-        hh[8..12].copy_from_slice(&crc32.to_le_bytes());
-
-        self.output.seek(std::io::SeekFrom::Start(0))?;
+        let hh = signature_header(
+            header_pos - self.signature_offset - SIGNATURE_HEADER_SIZE,
+            &header,
+        )?;
+
+        self.output
+            .seek(std::io::SeekFrom::Start(self.signature_offset))?;
         self.output.write_all(&hh)?;
+        self.output
+            .seek(std::io::SeekFrom::Start(header_pos + header.len() as u64))?;
         Ok(self.output)
     }
 
+    /// Finishes the compression with the headers before the packed streams, so the archive
+    /// can be read without seeking back, see [SevenZReader::from_stream](crate::SevenZReader::from_stream).
+    ///
+    /// Returns the signature header and the headers padded to the new position of the packed
+    /// streams, followed by the position and length of the packed streams in the output.
+    pub(crate) fn finish_header_first(mut self) -> std::io::Result<(Vec<u8>, u64, u64, W)> {
+        const MAX_LAYOUT_PASSES: usize = 16;
+        let pack_start = self.signature_offset + SIGNATURE_HEADER_SIZE + self.pack_info.pos;
+        let pack_len = self.output.stream_position()? - pack_start;
+        // positions after the signature header of the encoded header and of the packed streams,
+        // which only grow until the headers before them fit
+        let (mut encoded_pos, mut pack_pos) = (0, 0);
+        for _ in 0..MAX_LAYOUT_PASSES {
+            self.pack_info.pos = pack_pos;
+            let (header, encoded) = self.encode_header(encoded_pos)?;
+            if header.len() as u64 <= encoded_pos && encoded_pos + encoded.len() as u64 <= pack_pos
+            {
+                let mut headers = signature_header(0, &header)?.to_vec();
+                headers.extend_from_slice(&header);
+                headers.resize((SIGNATURE_HEADER_SIZE + encoded_pos) as usize, 0);
+                headers.extend_from_slice(&encoded);
+                headers.resize((SIGNATURE_HEADER_SIZE + pack_pos) as usize, 0);
+                return Ok((headers, pack_start, pack_len, self.output));
+            }
+            encoded_pos = encoded_pos.max(header.len() as u64);
+            pack_pos = pack_pos.max(encoded_pos + encoded.len() as u64);
+        }
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "unable to place the headers before the packed streams",
+        ))
+    }
+
     fn write_header<H: Write>(&mut self, header: &mut H) -> std::io::Result<()> {
         header.write_u8(K_HEADER)?;
         header.write_u8(K_MAIN_STREAMS_INFO)?;
@@ -401,13 +923,23 @@ impl<W: Write + Seek> SevenZWriter<W> {
     }
 
     fn write_encoded_header<H: Write>(&mut self, header: &mut H) -> std::io::Result<()> {
+        let position = self.output.stream_position()?;
+        let (next_header, encoded) =
+            self.encode_header(position - self.signature_offset - SIGNATURE_HEADER_SIZE)?;
+        self.output.write_all(&encoded)?;
+        header.write_all(&next_header)
+    }
+
+    /// Returns the next header and the packed stream of the encoded header,
+    /// which is empty if the header isn't encoded.
+    /// `pos` is the position of the packed stream after the signature header.
+    fn encode_header(&mut self, pos: u64) -> std::io::Result<(Vec<u8>, Vec<u8>)> {
         let mut raw_header = Vec::with_capacity(64 * 1024);
         self.write_header(&mut raw_header)?;
-        let mut pack_info = PackInfo::default();
-
-        let position = self.output.stream_position()?;
-        let pos = position - SIGNATURE_HEADER_SIZE;
-        pack_info.pos = pos;
+        let mut pack_info = PackInfo {
+            pos,
+            ..Default::default()
+        };
 
         let mut more_sizes = vec![];
         let size = raw_header.len() as u64;
@@ -441,10 +973,9 @@ impl<W: Write + Seek> SevenZWriter<W> {
         let compress_size = *compressed.bytes_written;
         if compress_size as u64 + 20 >= size {
             // compression made it worse. Write raw data
-            header.write_all(&raw_header)?;
-            return Ok(());
+            return Ok((raw_header, Vec::new()));
         }
-        self.output.write_all(&encoded_data[..compress_size])?;
+        encoded_data.truncate(compress_size);
 
         pack_info.add_stream(compress_size as u64, compress_crc);
 
@@ -454,15 +985,16 @@ impl<W: Write + Seek> SevenZWriter<W> {
         sizes.push(size);
         unpack_info.add(methods, sizes, crc);
 
+        let mut header = Vec::with_capacity(128);
         header.write_u8(K_ENCODED_HEADER)?;
 
-        pack_info.write_to(header)?;
-        unpack_info.write_to(header)?;
-        unpack_info.write_substreams(header)?;
+        pack_info.write_to(&mut header)?;
+        unpack_info.write_to(&mut header)?;
+        unpack_info.write_substreams(&mut header)?;
 
         header.write_u8(K_END)?;
 
-        Ok(())
+        Ok((header, encoded_data))
     }
 
     fn write_streams_info<H: Write>(&mut self, header: &mut H) -> std::io::Result<()> {
@@ -602,6 +1134,33 @@ impl<W: Write + Seek> SevenZWriter<W> {
     );
 }
 
+/// Returns the signature header pointing to `header` at `header_offset` after it.
+fn signature_header(
+    header_offset: u64,
+    header: &[u8],
+) -> std::io::Result<[u8; SIGNATURE_HEADER_SIZE as usize]> {
+    let crc32 = CRC32.checksum(header);
+    let mut hh = [0u8; SIGNATURE_HEADER_SIZE as usize];
+    {
+        let mut hhw = hh.as_mut_slice();
+        //sig
+        hhw.write_all(SEVEN_Z_SIGNATURE)?;
+        //version
+        hhw.write_u8(0)?;
+        hhw.write_u8(2)?;
+        //placeholder for crc: index = 8
+        hhw.write_u32::<LittleEndian>(0)?;
+
+        // start header
+        hhw.write_u64::<LittleEndian>(header_offset)?;
+        hhw.write_u64::<LittleEndian>(0xffffffff & header.len() as u64)?;
+        hhw.write_u32::<LittleEndian>(crc32)?;
+    }
+    let crc32 = CRC32.checksum(&hh[12..]);
+    hh[8..12].copy_from_slice(&crc32.to_le_bytes());
+    Ok(hh)
+}
+
 pub(crate) fn write_u64<W: Write>(header: &mut W, mut value: u64) -> std::io::Result<()> {
     let mut first = 0;
     let mut mask = 0x80;
@@ -643,6 +1202,38 @@ fn write_bit_set<W: Write>(mut write: W, bs: &BitSet) -> std::io::Result<()> {
     Ok(())
 }
 
+/// Moves the filters behind the other methods, so they are applied to the data first.
+fn filters_last(methods: &[SevenZMethodConfiguration]) -> Vec<SevenZMethodConfiguration> {
+    let (filters, mut methods): (Vec<_>, Vec<_>) =
+        methods.iter().cloned().partition(|m| m.method.is_filter());
+    methods.extend(filters);
+    methods
+}
+
+fn entries_names(entries: &[SevenZArchiveEntry]) -> String {
+    let mut names = String::with_capacity(512);
+    for ele in entries.iter() {
+        names.push_str(&ele.name);
+        names.push(';');
+        if names.len() > 512 {
+            break;
+        }
+    }
+    names
+}
+
+/// A folder written by [SevenZWriter::encode_pack_stream].
+struct PackStream {
+    /// Crc of the unpacked data.
+    crc: u32,
+    /// Size of the unpacked data.
+    size: u64,
+    folder: Folder,
+    /// Sizes of the packed streams of `folder`, in the order they were written.
+    pack_sizes: Vec<u64>,
+    pack_crcs: Vec<u32>,
+}
+
 struct CompressWrapWriter<'a, W> {
     writer: W,
     crc: crc::Digest<'static, u32>,
@@ -0,0 +1,302 @@
+use std::collections::HashSet;
+
+use super::*;
+use crate::{password::Password, SevenZReader};
+
+/// Removes and renames entries of an existing archive, like the `d` and `rn` commands of 7-Zip.
+///
+/// Blocks whose entries are all kept are copied into the new archive byte-for-byte,
+/// only blocks that lose some of their entries are decoded and encoded again.
+///
+/// # Examples
+/// ```no_run
+/// use sevenz_rust::*;
+/// let mut editor = ArchiveEditor::open("path/to/src.7z", Password::empty()).expect("open ok");
+/// editor.remove("old.txt").expect("removed");
+/// editor.rename("dir", "new_dir").expect("renamed");
+/// let mut sz = SevenZWriter::create("path/to/dest.7z").expect("create writer ok");
+/// editor.write_to(&mut sz).expect("edit ok");
+/// sz.finish().expect("done");
+/// ```
+pub struct ArchiveEditor<R: Read + Seek> {
+    source: R,
+    archive: Archive,
+    password: Vec<u8>,
+    removed: BitSet,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ArchiveEditor<File> {
+    #[inline]
+    pub fn open(path: impl AsRef<Path>, password: Password) -> Result<Self> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| Error::file_open(e, path.as_ref().to_string_lossy().to_string()))?;
+        let len = file.metadata().map(|m| m.len()).map_err(Error::io)?;
+        Self::new(file, len, password)
+    }
+}
+
+impl<R: Read + Seek> ArchiveEditor<R> {
+    pub fn new(mut source: R, reader_len: u64, password: Password) -> Result<Self> {
+        let password = password.to_vec();
+        let archive = Archive::read(&mut source, reader_len, &password)?;
+        Ok(Self {
+            source,
+            archive,
+            password,
+            removed: BitSet::new(),
+        })
+    }
+
+    /// The archive being edited, with renamed entries already carrying their new names.
+    #[inline]
+    pub fn archive(&self) -> &Archive {
+        &self.archive
+    }
+
+    /// Whether the entry at `file_index` in [Archive::files] has been removed.
+    #[inline]
+    pub fn is_removed(&self, file_index: usize) -> bool {
+        self.removed.contains(file_index)
+    }
+
+    /// Removes the entry named `name`. If it is a directory, all entries below it are removed too.
+    pub fn remove(&mut self, name: &str) -> Result<&mut Self> {
+        let mut found = false;
+        for (i, file) in self.archive.files.iter().enumerate() {
+            if !self.removed.contains(i) && path_relative_to(file.name(), name).is_some() {
+                self.removed.insert(i);
+                found = true;
+            }
+        }
+        if !found {
+            return Err(Error::other(format!("entry not found: {}", name)));
+        }
+        Ok(self)
+    }
+
+    /// Renames the entry `from` to `to`. If it is a directory, all entries below it are moved along.
+    ///
+    /// Fails if a renamed entry would get the name of another entry.
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<&mut Self> {
+        let mut renamed = Vec::new();
+        for (i, file) in self.archive.files.iter().enumerate() {
+            if self.removed.contains(i) {
+                continue;
+            }
+            if let Some(rest) = path_relative_to(file.name(), from) {
+                let name = if rest.is_empty() {
+                    to.to_string()
+                } else {
+                    format!("{}/{}", to, rest)
+                };
+                renamed.push((i, name));
+            }
+        }
+        if renamed.is_empty() {
+            return Err(Error::other(format!("entry not found: {}", from)));
+        }
+        let moved = renamed.iter().map(|(i, _)| *i).collect::<BitSet>();
+        let others = self
+            .archive
+            .files
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.removed.contains(*i) && !moved.contains(*i))
+            .map(|(_, file)| file.name())
+            .collect::<HashSet<_>>();
+        if let Some((_, name)) = renamed
+            .iter()
+            .find(|(_, name)| others.contains(name.as_str()))
+        {
+            return Err(Error::other(format!("entry already exists: {}", name)));
+        }
+        for (i, name) in renamed {
+            self.archive.files[i].name = name;
+        }
+        Ok(self)
+    }
+
+    /// Writes all entries that haven't been removed into `dest`.
+    ///
+    /// Blocks that need to be encoded again use the content methods of `dest`.
+    /// Fails if such a block is encrypted and these methods don't encrypt,
+    /// instead of writing its entries unencrypted.
+    pub fn write_to<W: Write + Seek>(mut self, dest: &mut SevenZWriter<W>) -> Result<()> {
+        let archive = &self.archive;
+        let mut folder_files = vec![Vec::new(); archive.folders.len()];
+        for (i, file) in archive.files.iter().enumerate() {
+            if let (true, Some(folder_index)) =
+                (file.has_stream, archive.stream_map.file_folder_index[i])
+            {
+                folder_files[folder_index].push(i);
+            }
+        }
+
+        let mut files = archive.files.clone();
+        for (folder_index, file_indices) in folder_files.iter().enumerate() {
+            let kept = file_indices
+                .iter()
+                .filter(|i| !self.removed.contains(**i))
+                .count();
+            if kept == 0 {
+                continue;
+            }
+            if kept == file_indices.len() {
+                self.copy_folder(folder_index, file_indices, dest)?;
+            } else {
+                self.encode_folder(folder_index, file_indices, &mut files, dest)?;
+            }
+        }
+
+        dest.files.extend(
+            files
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| !self.removed.contains(*i))
+                .map(|(_, file)| file),
+        );
+        Ok(())
+    }
+
+    fn copy_folder<W: Write + Seek>(
+        &mut self,
+        folder_index: usize,
+        file_indices: &[usize],
+        dest: &mut SevenZWriter<W>,
+    ) -> Result<()> {
+        let archive = &self.archive;
+        let folder = &archive.folders[folder_index];
+        let first_pack_stream_index =
+            archive.stream_map.folder_first_pack_stream_index[folder_index];
+        for i in first_pack_stream_index..first_pack_stream_index + folder.packed_streams.len() {
+            let pack_pos = archive.signature_offset
+                + SIGNATURE_HEADER_SIZE
+                + archive.pack_pos
+                + archive.stream_map.pack_stream_offsets[i];
+            let pack_size = archive.pack_sizes[i];
+            self.source
+                .seek(SeekFrom::Start(pack_pos))
+                .map_err(Error::io)?;
+            let mut copied = 0;
+            let mut w = CompressWrapWriter::new(&mut dest.output, &mut copied);
+            std::io::copy(&mut (&mut self.source).take(pack_size), &mut w)
+                .map_err(|e| Error::io_msg(e, format!("Copy pack stream:{}", i)))?;
+            let crc = w.crc_value();
+            if copied as u64 != pack_size {
+                return Err(Error::other(format!("pack stream {} is truncated", i)));
+            }
+            dest.pack_info.add_stream(pack_size, crc);
+        }
+
+        let (sizes, crcs) = file_indices
+            .iter()
+            .map(|i| &archive.files[*i])
+            .map(|f| (f.size, f.has_crc.then_some(f.crc as u32)))
+            .unzip();
+        dest.unpack_info.add_folder(folder.clone(), sizes, crcs);
+        Ok(())
+    }
+
+    fn encode_folder<W: Write + Seek>(
+        &mut self,
+        folder_index: usize,
+        file_indices: &[usize],
+        files: &mut [SevenZArchiveEntry],
+        dest: &mut SevenZWriter<W>,
+    ) -> Result<()> {
+        let archive = &self.archive;
+        let is_aes = |id: &[u8]| id == SevenZMethod::ID_AES256SHA256;
+        let encrypted = archive.folders[folder_index]
+            .coders
+            .iter()
+            .any(|coder| is_aes(coder.decompression_method_id()));
+        if encrypted && !dest.content_methods.iter().any(|m| is_aes(m.method.id())) {
+            return Err(Error::other(format!(
+                "block {} is encrypted, set an AES content method on dest to encode it again",
+                folder_index
+            )));
+        }
+        let removed = &self.removed;
+        let kept = file_indices
+            .iter()
+            .copied()
+            .filter(|i| !removed.contains(*i))
+            .collect::<Vec<_>>();
+        let (mut folder_reader, _) = SevenZReader::<R>::build_decode_stack(
+            &mut self.source,
+            archive,
+            folder_index,
+            &self.password,
+            &Default::default(),
+        )?;
+
+        let mut sub_stream_crcs = Vec::with_capacity(kept.len());
+        let stream = SevenZWriter::<W>::encode_pack_stream(
+            &mut dest.output,
+            &dest.content_methods,
+            || {
+                let entries = kept.iter().map(|i| archive.files[*i].clone());
+                format!(
+                    "Encode entries:{}",
+                    entries_names(&entries.collect::<Vec<_>>())
+                )
+            },
+            |w| {
+                for &i in file_indices {
+                    let file = &archive.files[i];
+                    let mut entry_reader = (&mut folder_reader).take(file.size);
+                    let copied = if removed.contains(i) {
+                        std::io::copy(&mut entry_reader, &mut std::io::sink())?
+                    } else {
+                        let mut len = 0;
+                        let mut crc_writer = CompressWrapWriter::new(&mut *w, &mut len);
+                        let copied = std::io::copy(&mut entry_reader, &mut crc_writer)?;
+                        let crc = crc_writer.crc_value();
+                        if file.has_crc && crc as u64 != file.crc {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::Other,
+                                Error::ChecksumVerificationFailed,
+                            ));
+                        }
+                        sub_stream_crcs.push(crc);
+                        copied
+                    };
+                    if copied != file.size {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::UnexpectedEof,
+                            format!("entry {} is truncated", file.name()),
+                        ));
+                    }
+                }
+                Ok(())
+            },
+        )
+        .map_err(|e| e.maybe_bad_password(!self.password.is_empty()))?;
+
+        let mut sub_stream_sizes = Vec::with_capacity(kept.len());
+        for (i, crc) in kept.iter().zip(sub_stream_crcs.iter()) {
+            let file = &mut files[*i];
+            file.crc = *crc as u64;
+            file.has_crc = true;
+            file.compressed_size = 0;
+            sub_stream_sizes.push(file.size);
+        }
+        dest.add_pack_stream(
+            stream,
+            sub_stream_sizes,
+            sub_stream_crcs.into_iter().map(Some).collect(),
+        );
+        Ok(())
+    }
+}
+
+/// Returns the part of `name` below `dir`, or an empty string if `name` equals `dir`.
+fn path_relative_to<'a>(name: &'a str, dir: &str) -> Option<&'a str> {
+    let rest = name.strip_prefix(dir)?;
+    if rest.is_empty() {
+        Some(rest)
+    } else {
+        rest.strip_prefix('/')
+    }
+}
@@ -35,6 +35,9 @@ impl PackInfo {
             let mut temp = Vec::with_capacity(self.len());
             write_bit_set(&mut temp, &crc_define_bits)?;
             header.write_all(&temp)?;
+            for crc in self.crcs.iter().filter(|crc| **crc != 0) {
+                header.write_u32::<LittleEndian>(*crc)?;
+            }
         }
 
         header.write_u8(K_END)?;
@@ -52,6 +52,11 @@ impl<R> SeqReader<R> {
     pub fn reader_len(&self) -> usize {
         self.readers.len()
     }
+
+    /// Returns the index of the reader that is read next.
+    pub(crate) fn current(&self) -> usize {
+        self.current
+    }
 }
 
 impl<R: Read> Read for SeqReader<R> {
@@ -120,7 +125,8 @@ impl<R> SourceReader<R> {
 
 pub(crate) struct LazyFileReader {
     path: PathBuf,
-    reader: Option<File>,
+    symlink: bool,
+    reader: Option<Box<dyn Read + Send>>,
     end: bool,
 }
 
@@ -128,10 +134,29 @@ impl LazyFileReader {
     pub fn new(path: PathBuf) -> Self {
         Self {
             path,
+            symlink: false,
             reader: None,
             end: false,
         }
     }
+
+    /// Reads the target of the symbolic link at `path` instead of the file it points to.
+    pub fn new_symlink(path: PathBuf) -> Self {
+        Self {
+            symlink: true,
+            ..Self::new(path)
+        }
+    }
+}
+
+/// Returns the target of the symbolic link at `path` with `/` separators,
+/// as p7zip stores it as the content of the entry.
+pub(crate) fn symlink_target(path: &Path) -> io::Result<Vec<u8>> {
+    let target = std::fs::read_link(path)?;
+    let target = target.to_string_lossy();
+    #[cfg(windows)]
+    let target = target.replace('\\', "/");
+    Ok(target.as_bytes().to_vec())
 }
 
 impl Read for LazyFileReader {
@@ -140,7 +165,11 @@ impl Read for LazyFileReader {
             return Ok(0);
         }
         if self.reader.is_none() {
-            self.reader = Some(File::open(&self.path)?);
+            self.reader = Some(if self.symlink {
+                Box::new(io::Cursor::new(symlink_target(&self.path)?))
+            } else {
+                Box::new(File::open(&self.path)?)
+            });
         }
         let n = self.reader.as_mut().unwrap().read(buf)?;
         if n == 0 {
@@ -0,0 +1,134 @@
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+use super::{Result, SevenZWriter};
+
+/// Lets [SevenZWriter] write to a `sink` that can't seek, like a pipe, stdout
+/// or an HTTP response body.
+///
+/// The start header at the beginning of a 7z archive points to the header written last,
+/// so the archive is spooled into `spool` and copied to the sink by
+/// [SevenZWriter::finish_streaming]. The default spool is memory, pass a temporary file to
+/// [SevenZWriter::new_streaming_with_spool] for large archives.
+pub struct SpoolWriter<W, S = Cursor<Vec<u8>>> {
+    sink: W,
+    spool: S,
+    len: u64,
+    header_first: bool,
+}
+
+impl<W: Write, S: Read + Write + Seek> SpoolWriter<W, S> {
+    /// Copies the spooled archive to the sink and returns it.
+    ///
+    /// This is how to get the archive out of the writer returned by [SevenZWriter::finish],
+    /// which always places the header at the end.
+    /// [SevenZWriter::finish_streaming] does both steps.
+    pub fn into_sink(mut self) -> std::io::Result<W> {
+        self.spool.seek(SeekFrom::Start(0))?;
+        self.into_sink_from_current()
+    }
+
+    /// Copies `len` bytes from the current position of the spool to the sink and returns it.
+    fn into_sink_from_current(mut self) -> std::io::Result<W> {
+        let copied = std::io::copy(&mut (&mut self.spool).take(self.len), &mut self.sink)?;
+        if copied != self.len {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "spool is shorter than the archive written to it",
+            ));
+        }
+        self.sink.flush()?;
+        Ok(self.sink)
+    }
+
+    /// Writes `headers` to the sink, followed by the spooled packed streams.
+    fn into_sink_header_first(
+        mut self,
+        headers: &[u8],
+        pack_start: u64,
+        pack_len: u64,
+    ) -> std::io::Result<W> {
+        self.sink.write_all(headers)?;
+        self.spool.seek(SeekFrom::Start(pack_start))?;
+        self.len = pack_len;
+        self.into_sink_from_current()
+    }
+}
+
+impl<W, S: Write + Seek> Write for SpoolWriter<W, S> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let size = self.spool.write(buf)?;
+        self.len = self.len.max(self.spool.stream_position()?);
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.spool.flush()
+    }
+}
+
+impl<W, S: Seek> Seek for SpoolWriter<W, S> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match pos {
+            // the spool may be longer than the archive, e.g. a reused temporary file
+            SeekFrom::End(offset) => {
+                let pos = self.len.checked_add_signed(offset).ok_or_else(|| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidInput, "SeekBeforeStart")
+                })?;
+                self.spool.seek(SeekFrom::Start(pos))
+            }
+            pos => self.spool.seek(pos),
+        }
+    }
+}
+
+impl<W: Write> SevenZWriter<SpoolWriter<W>> {
+    /// Prepares writer to write a 7z archive to `sink`, which doesn't need to be [Seek].
+    /// The archive is spooled in memory until [finish_streaming](Self::finish_streaming).
+    /// # Examples
+    /// ```no_run
+    /// use sevenz_rust::*;
+    /// let mut sz = SevenZWriter::new_streaming(std::io::stdout()).unwrap();
+    /// sz.push_source_path("path/to/compress", |_| true).unwrap();
+    /// sz.finish_streaming().unwrap();
+    /// ```
+    pub fn new_streaming(sink: W) -> Result<Self> {
+        Self::new_streaming_with_spool(sink, Cursor::new(Vec::new()))
+    }
+}
+
+impl<W: Write, S: Read + Write + Seek> SevenZWriter<SpoolWriter<W, S>> {
+    /// Like [new_streaming](SevenZWriter::new_streaming), spooling the archive into `spool`,
+    /// e.g. a temporary file, instead of memory.
+    pub fn new_streaming_with_spool(sink: W, spool: S) -> Result<Self> {
+        let mut sz = SevenZWriter::new(SpoolWriter {
+            sink,
+            spool,
+            len: 0,
+            header_first: false,
+        })?;
+        sz.truncate = Some(|output, len| {
+            output.len = len;
+            Ok(())
+        });
+        Ok(sz)
+    }
+
+    /// Sets whether to place the headers before the packed streams, so the archive can be
+    /// extracted while it is received, without spooling it, by
+    /// [SevenZReader::from_stream](crate::SevenZReader::from_stream).
+    /// The default is `false`, which places the header at the end like 7-Zip.
+    pub fn set_header_first(&mut self, enabled: bool) -> &mut Self {
+        self.output.header_first = enabled;
+        self
+    }
+
+    /// Finishes the compression and copies the archive to the sink, which is returned.
+    pub fn finish_streaming(self) -> std::io::Result<W> {
+        if self.output.header_first {
+            let (headers, pack_start, pack_len, output) = self.finish_header_first()?;
+            output.into_sink_header_first(&headers, pack_start, pack_len)
+        } else {
+            self.finish()?.into_sink()
+        }
+    }
+}
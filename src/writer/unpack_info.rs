@@ -1,7 +1,7 @@
 use std::{io::Write, sync::Arc};
 
 use super::*;
-use crate::SevenZMethodConfiguration;
+use crate::{folder::*, SevenZMethod, SevenZMethodConfiguration};
 #[derive(Debug, Clone, Default)]
 pub struct UnpackInfo {
     pub folders: Vec<FolderInfo>,
@@ -10,30 +10,23 @@ pub struct UnpackInfo {
 impl UnpackInfo {
     pub fn add(&mut self, methods: Arc<Vec<SevenZMethodConfiguration>>, sizes: Vec<u64>, crc: u32) {
         self.folders.push(FolderInfo {
-            methods,
-            sizes,
-            crc,
-            num_sub_unpack_streams: 1,
+            folder: FolderInfo::folder_of_methods(&methods, sizes, crc),
             ..Default::default()
         })
     }
 
-    pub fn add_multiple(
+    /// Adds a folder holding `sub_stream_sizes.len()` entries.
+    pub fn add_folder(
         &mut self,
-        methods: Arc<Vec<SevenZMethodConfiguration>>,
-        sizes: Vec<u64>,
-        crc: u32,
-        num_sub_unpack_streams: u64,
+        mut folder: Folder,
         sub_stream_sizes: Vec<u64>,
-        sub_stream_crcs: Vec<u32>,
+        sub_stream_crcs: Vec<Option<u32>>,
     ) {
+        folder.num_unpack_sub_streams = sub_stream_sizes.len();
         self.folders.push(FolderInfo {
-            methods,
-            sizes,
-            crc,
-            num_sub_unpack_streams,
-            sub_stream_crcs,
+            folder,
             sub_stream_sizes,
+            sub_stream_crcs,
         })
     }
 
@@ -42,20 +35,23 @@ impl UnpackInfo {
         header.write_u8(K_FOLDER)?;
         write_u64(header, self.folders.len() as u64)?;
         header.write_u8(0)?;
-        let mut cache = Vec::with_capacity(32);
         for folder in self.folders.iter() {
-            folder.write_to(header, &mut cache)?;
+            folder.write_to(header)?;
         }
         header.write_u8(K_CODERS_UNPACK_SIZE)?;
         for folder in self.folders.iter() {
-            for size in folder.sizes.iter().copied() {
+            for size in folder.folder.unpack_sizes.iter().copied() {
                 write_u64(header, size)?;
             }
         }
-        header.write_u8(K_CRC)?;
-        header.write_u8(1)?; //all defined
-        for folder in self.folders.iter() {
-            header.write_u32::<LittleEndian>(folder.crc)?;
+        let crcs = self
+            .folders
+            .iter()
+            .map(|f| f.folder.has_crc.then_some(f.folder.crc as u32))
+            .collect::<Vec<_>>();
+        if crcs.iter().any(Option::is_some) {
+            header.write_u8(K_CRC)?;
+            write_digests(header, &crcs)?;
         }
         header.write_u8(K_END)?;
         Ok(())
@@ -66,7 +62,7 @@ impl UnpackInfo {
 
         header.write_u8(K_NUM_UNPACK_STREAM)?;
         for f in &self.folders {
-            write_u64(header, f.num_sub_unpack_streams)?;
+            write_u64(header, f.folder.num_unpack_sub_streams as u64)?;
         }
         header.write_u8(K_SIZE)?;
         for f in &self.folders {
@@ -78,16 +74,16 @@ impl UnpackInfo {
                 write_u64(header, size)?;
             }
         }
-        header.write_u8(K_CRC)?;
-        header.write_u8(1)?; // all crc defined
+        let mut crcs = Vec::with_capacity(self.folders.len());
         for f in &self.folders {
-            if f.sub_stream_crcs.len() <= 1 && f.crc != 0 {
+            if f.folder.num_unpack_sub_streams == 1 && f.folder.has_crc {
                 continue;
             }
-            for i in 0..f.sub_stream_crcs.len() {
-                let crc = f.sub_stream_crcs[i];
-                header.write_u32::<LittleEndian>(crc)?;
-            }
+            crcs.extend(f.sub_stream_crcs.iter().copied());
+        }
+        if crcs.iter().any(Option::is_some) {
+            header.write_u8(K_CRC)?;
+            write_digests(header, &crcs)?;
         }
         header.write_u8(K_END)?;
 
@@ -95,50 +91,170 @@ impl UnpackInfo {
     }
 }
 
+fn write_digests<H: Write>(header: &mut H, crcs: &[Option<u32>]) -> std::io::Result<()> {
+    if crcs.iter().all(Option::is_some) {
+        header.write_u8(1)?; // all defined
+    } else {
+        header.write_u8(0)?; // not all defined
+        let mut defined = BitSet::with_capacity(crcs.len());
+        for (i, crc) in crcs.iter().enumerate() {
+            if crc.is_some() {
+                defined.insert(i);
+            }
+        }
+        write_bit_set(&mut *header, &defined)?;
+    }
+    for crc in crcs.iter().flatten() {
+        header.write_u32::<LittleEndian>(*crc)?;
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct FolderInfo {
-    pub methods: Arc<Vec<SevenZMethodConfiguration>>,
-    // pub bind_pairs: Vec<BindPair>,
-    pub sizes: Vec<u64>,
-    pub crc: u32,
-    pub num_sub_unpack_streams: u64,
+    pub folder: Folder,
     pub sub_stream_sizes: Vec<u64>,
-    pub sub_stream_crcs: Vec<u32>,
+    pub sub_stream_crcs: Vec<Option<u32>>,
+}
+
+fn coder_of_method(mc: &SevenZMethodConfiguration) -> Coder {
+    let mut temp = [0u8; 256];
+    let id = mc.method.id();
+    let props = encoders::get_options_as_properties(mc.method, mc.options.as_ref(), &mut temp);
+    let mut coder = Coder::default();
+    coder.id_size = id.len();
+    coder.decompression_method_id_mut().copy_from_slice(id);
+    coder.num_in_streams = 1;
+    coder.num_out_streams = 1;
+    coder.properties = props.to_vec();
+    coder
 }
 
 impl FolderInfo {
-    pub fn write_to<W: Write>(&self, header: &mut W, cache: &mut Vec<u8>) -> std::io::Result<()> {
-        cache.clear();
-        let mut num_coders = 0;
-        for mc in self.methods.iter() {
-            num_coders += 1;
-            self.write_single_codec(mc, cache)?;
-        }
-        write_u64(header, num_coders as u64)?;
-        header.write_all(cache)?;
-        for i in 0..num_coders - 1 {
-            write_u64(header, i as u64 + 1)?;
-            write_u64(header, i as u64)?;
+    /// Builds the folder of a coder chain where `methods[0]` writes the packed stream.
+    pub fn folder_of_methods(
+        methods: &[SevenZMethodConfiguration],
+        unpack_sizes: Vec<u64>,
+        crc: u32,
+    ) -> Folder {
+        let coders = methods.iter().map(coder_of_method).collect::<Vec<_>>();
+        let bind_pairs = (1..coders.len() as u64)
+            .map(|i| BindPair {
+                in_index: i,
+                out_index: i - 1,
+            })
+            .collect();
+        Folder {
+            total_input_streams: coders.len(),
+            total_output_streams: coders.len(),
+            coders,
+            bind_pairs,
+            packed_streams: vec![0],
+            unpack_sizes,
+            has_crc: true,
+            crc: crc as u64,
+            num_unpack_sub_streams: 1,
+        }
+    }
+
+    /// Builds the folder of a BCJ2 coder whose main stream is encoded by the coder chain
+    /// `main_methods` and whose call and jump streams are each encoded by `side_method`.
+    /// The range coder stream is stored as is.
+    ///
+    /// The packed streams are in the order main, call, jump, range coder and
+    /// `unpack_sizes` are those of `main_methods`, the call and jump coders and BCJ2.
+    pub fn bcj2_folder_of_methods(
+        main_methods: &[SevenZMethodConfiguration],
+        side_method: &SevenZMethodConfiguration,
+        unpack_sizes: Vec<u64>,
+        crc: u32,
+    ) -> Folder {
+        let k = main_methods.len() as u64;
+        let mut coders = main_methods.iter().map(coder_of_method).collect::<Vec<_>>();
+        coders.push(coder_of_method(side_method));
+        coders.push(coder_of_method(side_method));
+        let mut bcj2 = Coder::default();
+        bcj2.id_size = SevenZMethod::ID_BCJ2.len();
+        bcj2.decompression_method_id_mut()
+            .copy_from_slice(SevenZMethod::ID_BCJ2);
+        bcj2.num_in_streams = 4;
+        bcj2.num_out_streams = 1;
+        coders.push(bcj2);
+
+        // in streams of the main chain, the call and jump coders are numbered like the coders,
+        // the 4 in streams of BCJ2 follow them.
+        let (main_in, call_in, jump_in, rc_in) = (k + 2, k + 3, k + 4, k + 5);
+        let mut bind_pairs = (1..k)
+            .map(|i| BindPair {
+                in_index: i,
+                out_index: i - 1,
+            })
+            .collect::<Vec<_>>();
+        if k > 0 {
+            bind_pairs.push(BindPair {
+                in_index: main_in,
+                out_index: k - 1,
+            });
+        }
+        bind_pairs.push(BindPair {
+            in_index: call_in,
+            out_index: k,
+        });
+        bind_pairs.push(BindPair {
+            in_index: jump_in,
+            out_index: k + 1,
+        });
+        let main_packed = if k > 0 { 0 } else { main_in };
+        Folder {
+            total_input_streams: k as usize + 6,
+            total_output_streams: coders.len(),
+            coders,
+            bind_pairs,
+            packed_streams: vec![main_packed, k, k + 1, rc_in],
+            unpack_sizes,
+            has_crc: true,
+            crc: crc as u64,
+            num_unpack_sub_streams: 1,
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, header: &mut W) -> std::io::Result<()> {
+        let folder = &self.folder;
+        write_u64(header, folder.coders.len() as u64)?;
+        for coder in folder.coders.iter() {
+            Self::write_single_codec(coder, header)?;
+        }
+        for bp in folder.bind_pairs.iter() {
+            write_u64(header, bp.in_index)?;
+            write_u64(header, bp.out_index)?;
+        }
+        if folder.packed_streams.len() > 1 {
+            for index in folder.packed_streams.iter() {
+                write_u64(header, *index)?;
+            }
         }
         Ok(())
     }
 
-    fn write_single_codec<H: Write>(
-        &self,
-        mc: &SevenZMethodConfiguration,
-        out: &mut H,
-    ) -> std::io::Result<()> {
-        let id = mc.method.id();
-        let mut temp = [0u8; 256];
-        let props = encoders::get_options_as_properties(mc.method, mc.options.as_ref(), &mut temp);
+    fn write_single_codec<H: Write>(coder: &Coder, out: &mut H) -> std::io::Result<()> {
+        let id = coder.decompression_method_id();
+        let props = &coder.properties;
+        let is_simple = coder.num_in_streams == 1 && coder.num_out_streams == 1;
         let mut codec_flags = id.len() as u8;
+        if !is_simple {
+            codec_flags |= 0x10;
+        }
         if !props.is_empty() {
             codec_flags |= 0x20;
         }
         out.write_u8(codec_flags)?;
         out.write_all(id)?;
+        if !is_simple {
+            write_u64(out, coder.num_in_streams)?;
+            write_u64(out, coder.num_out_streams)?;
+        }
         if !props.is_empty() {
-            out.write_u8(props.len() as u8)?;
+            write_u64(out, props.len() as u64)?;
             out.write_all(props)?;
         }
         Ok(())
@@ -1,3 +1,4 @@
+#![allow(clippy::bool_assert_comparison)]
 use sevenz_rust::*;
 use std::collections::HashMap;
 use tempfile::*;
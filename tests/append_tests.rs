@@ -0,0 +1,197 @@
+#![cfg(feature = "compress")]
+use std::{collections::HashMap, fs::OpenOptions, io::Seek, path::Path};
+
+use sevenz_rust::*;
+use tempfile::tempdir;
+
+fn read_all_entries(path: &Path) -> HashMap<String, String> {
+    let mut contents = HashMap::new();
+    let mut sz = SevenZReader::open(path, Password::empty()).unwrap();
+    sz.for_each_entries(|entry, reader| {
+        contents.insert(entry.name().to_string(), std::io::read_to_string(reader)?);
+        Ok(true)
+    })
+    .unwrap();
+    contents
+}
+
+fn append_file(archive: &Path, name: &str, content: &str) {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(archive)
+        .unwrap();
+    let mut sz = SevenZWriter::open_for_append(file).unwrap();
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = name.to_string();
+    sz.push_archive_entry(entry, Some(content.as_bytes()))
+        .unwrap();
+    let mut file = sz.finish().unwrap();
+    let len = file.stream_position().unwrap();
+    file.set_len(len).unwrap();
+}
+
+#[test]
+fn append_entries_to_existing_archive() {
+    let temp_dir = tempdir().unwrap();
+    let folder = temp_dir.path().join("folder");
+    std::fs::create_dir(&folder).unwrap();
+    std::fs::write(folder.join("file1.txt"), "file1 with content").unwrap();
+    std::fs::write(folder.join("file2.txt"), "file2 with content").unwrap();
+    let dest = temp_dir.path().join("folder.7z");
+    let mut sz = SevenZWriter::create(&dest).unwrap();
+    sz.push_source_path(&folder, |_| true).unwrap();
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "empty.txt".to_string();
+    sz.push_archive_entry::<&[u8]>(entry, None).unwrap();
+    sz.finish().unwrap();
+
+    append_file(&dest, "file3.txt", "file3 with content");
+    append_file(&dest, "file4.txt", "file4 with content");
+
+    let archive = Archive::open(&dest).unwrap();
+    assert_eq!(archive.files.len(), 5);
+    assert_eq!(archive.folders.len(), 3);
+    let contents = read_all_entries(&dest);
+    assert_eq!(contents["file1.txt"], "file1 with content");
+    assert_eq!(contents["file2.txt"], "file2 with content");
+    assert_eq!(contents["empty.txt"], "");
+    assert_eq!(contents["file3.txt"], "file3 with content");
+    assert_eq!(contents["file4.txt"], "file4 with content");
+}
+
+#[test]
+fn append_keeps_multi_stream_folders() {
+    let temp_dir = tempdir().unwrap();
+    let dest = temp_dir.path().join("bcj2.7z");
+    std::fs::copy("tests/resources/7za433_7zip_lzma2_bcj2.7z", &dest).unwrap();
+    let mut before = HashMap::new();
+    let mut sz = SevenZReader::open(&dest, Password::empty()).unwrap();
+    sz.for_each_entries(|entry, reader| {
+        let mut content = Vec::new();
+        std::io::copy(reader, &mut content)?;
+        before.insert(entry.name().to_string(), content);
+        Ok(true)
+    })
+    .unwrap();
+
+    append_file(&dest, "appended.txt", "appended content");
+
+    let mut sz = SevenZReader::open(&dest, Password::empty()).unwrap();
+    assert_eq!(sz.archive().files.len(), before.len() + 1);
+    sz.for_each_entries(|entry, reader| {
+        let mut content = Vec::new();
+        std::io::copy(reader, &mut content)?;
+        if entry.name() == "appended.txt" {
+            assert_eq!(content, b"appended content");
+        } else {
+            assert_eq!(content, before[entry.name()]);
+        }
+        Ok(true)
+    })
+    .unwrap();
+}
+
+#[test]
+fn append_to_archive_after_stub() {
+    let temp_dir = tempdir().unwrap();
+    let dest = temp_dir.path().join("sfx.exe");
+    let stub = b"#!/bin/sh\necho self-extracting stub\nexit 0\n".repeat(100);
+    let mut sz = SevenZWriter::new_with_stub(std::fs::File::create(&dest).unwrap(), &stub).unwrap();
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "file1.txt".to_string();
+    sz.push_archive_entry(entry, Some(&b"file1 with content"[..]))
+        .unwrap();
+    sz.finish().unwrap();
+
+    let options = ReaderOptions::default().with_signature_search_limit(stub.len() as u64 + 1);
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&dest)
+        .unwrap();
+    let mut sz =
+        SevenZWriter::open_for_append_with_options(file, Password::empty(), options).unwrap();
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "file2.txt".to_string();
+    sz.push_archive_entry(entry, Some(&b"file2 with content"[..]))
+        .unwrap();
+    let mut file = sz.finish().unwrap();
+    let len = file.stream_position().unwrap();
+    file.set_len(len).unwrap();
+
+    let data = std::fs::read(&dest).unwrap();
+    assert_eq!(&data[..stub.len()], &stub[..]);
+    let file = std::fs::File::open(&dest).unwrap();
+    let mut sz = SevenZReader::new_with_options(file, len, Password::empty(), options).unwrap();
+    let mut contents = HashMap::new();
+    sz.for_each_entries(|entry, reader| {
+        contents.insert(entry.name().to_string(), std::io::read_to_string(reader)?);
+        Ok(true)
+    })
+    .unwrap();
+    assert_eq!(contents["file1.txt"], "file1 with content");
+    assert_eq!(contents["file2.txt"], "file2 with content");
+}
+
+#[cfg(feature = "aes256")]
+#[test]
+fn append_to_archive_with_encrypted_header() {
+    let temp_dir = tempdir().unwrap();
+    let dest = temp_dir.path().join("encrypted.7z");
+    let methods = || {
+        vec![
+            AesEncoderOptions::new("secret".into()).into(),
+            SevenZMethod::LZMA2.into(),
+        ]
+    };
+    let mut sz = SevenZWriter::create(&dest).unwrap();
+    sz.set_content_methods(methods());
+    // a header too small to compress is written unencrypted
+    for i in 0..50 {
+        let mut entry = SevenZArchiveEntry::new();
+        entry.name = format!("some/directory/file{i}.txt");
+        sz.push_archive_entry::<&[u8]>(entry, None).unwrap();
+    }
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "file1.txt".to_string();
+    sz.push_archive_entry(entry, Some(&b"file1 with content"[..]))
+        .unwrap();
+    sz.finish().unwrap();
+    assert!(Archive::open(&dest).is_err());
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&dest)
+        .unwrap();
+    assert!(SevenZWriter::open_for_append(file).is_err());
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&dest)
+        .unwrap();
+    let mut sz =
+        SevenZWriter::open_for_append_with_options(file, "secret".into(), ReaderOptions::default())
+            .unwrap();
+    sz.set_content_methods(methods());
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "file2.txt".to_string();
+    sz.push_archive_entry(entry, Some(&b"file2 with content"[..]))
+        .unwrap();
+    let mut file = sz.finish().unwrap();
+    let len = file.stream_position().unwrap();
+    file.set_len(len).unwrap();
+
+    // the new header is encrypted too
+    assert!(Archive::open(&dest).is_err());
+    let mut sz = SevenZReader::open(&dest, "secret".into()).unwrap();
+    let mut contents = HashMap::new();
+    sz.for_each_entries(|entry, reader| {
+        contents.insert(entry.name().to_string(), std::io::read_to_string(reader)?);
+        Ok(true)
+    })
+    .unwrap();
+    assert_eq!(contents["file1.txt"], "file1 with content");
+    assert_eq!(contents["file2.txt"], "file2 with content");
+}
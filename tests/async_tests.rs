@@ -0,0 +1,140 @@
+#![cfg(feature = "async")]
+use std::io::Cursor;
+
+use sevenz_rust::*;
+use tokio::io::AsyncReadExt;
+
+async fn read_all_async<R>(sz: &mut AsyncSevenZReader<R>) -> Vec<(String, Vec<u8>)>
+where
+    R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin,
+{
+    let mut result = Vec::new();
+    let mut entries = sz.entries();
+    while let Some(mut entry) = entries.next_entry().await.unwrap() {
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content).await.unwrap();
+        result.push((entry.entry().name().to_string(), content));
+    }
+    result
+}
+
+#[tokio::test]
+async fn async_read_matches_sync_read() {
+    let path = "tests/resources/two_files_with_content_lzma.7z";
+    let mut expected = Vec::new();
+    SevenZReader::open(path, Password::empty())
+        .unwrap()
+        .for_each_entries(|entry, reader| {
+            let mut content = Vec::new();
+            reader.read_to_end(&mut content)?;
+            expected.push((entry.name().to_string(), content));
+            Ok(true)
+        })
+        .unwrap();
+
+    let file = tokio::fs::File::open(path).await.unwrap();
+    let mut sz = AsyncSevenZReader::new(file, Password::empty())
+        .await
+        .unwrap();
+    assert_eq!(sz.archive().files.len(), expected.len());
+    assert_eq!(read_all_async(&mut sz).await, expected);
+}
+
+#[tokio::test]
+async fn async_entries_skip_unread_content() {
+    let file = tokio::fs::File::open("tests/resources/two_files_with_content_lzma.7z")
+        .await
+        .unwrap();
+    let mut sz = AsyncSevenZReader::new(file, Password::empty())
+        .await
+        .unwrap();
+    let mut entries = sz.entries();
+    let mut names = Vec::new();
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        names.push(entry.entry().name().to_string());
+    }
+    assert_eq!(names.len(), 2);
+}
+
+#[cfg(feature = "compress")]
+#[tokio::test]
+async fn async_write_and_read() {
+    let contents: Vec<(String, Vec<u8>)> = vec![
+        ("a.txt".to_string(), b"hello async".repeat(10_000)),
+        (
+            "b.bin".to_string(),
+            (0..200_000u32).map(|i| i as u8).collect(),
+        ),
+    ];
+    let mut sz = AsyncSevenZWriter::new(Cursor::new(Vec::new()));
+    for (name, content) in &contents {
+        let mut entry = SevenZArchiveEntry::new();
+        entry.name = name.clone();
+        entry.has_stream = true;
+        let pushed = sz
+            .push_archive_entry(entry, Some(content.as_slice()))
+            .await
+            .unwrap();
+        assert_eq!(pushed.size, content.len() as u64);
+    }
+    let mut dir = SevenZArchiveEntry::new();
+    dir.name = "dir".to_string();
+    dir.is_directory = true;
+    sz.push_archive_entry(dir, None::<&[u8]>).await.unwrap();
+    let bytes = sz.finish().await.unwrap().into_inner();
+
+    let mut sync_contents = Vec::new();
+    let len = bytes.len() as u64;
+    SevenZReader::new(Cursor::new(bytes.clone()), len, Password::empty())
+        .unwrap()
+        .for_each_entries(|entry, reader| {
+            if !entry.is_directory() {
+                let mut content = Vec::new();
+                reader.read_to_end(&mut content)?;
+                sync_contents.push((entry.name().to_string(), content));
+            }
+            Ok(true)
+        })
+        .unwrap();
+    assert_eq!(sync_contents, contents);
+
+    let mut sz = AsyncSevenZReader::new(Cursor::new(bytes), Password::empty())
+        .await
+        .unwrap();
+    let read = read_all_async(&mut sz).await;
+    assert_eq!(read[..2], contents[..]);
+    assert_eq!(read[2], ("dir".to_string(), Vec::new()));
+}
+
+#[cfg(feature = "compress")]
+#[tokio::test]
+async fn async_writer_is_poisoned_by_a_cancelled_push() {
+    /// A reader that never returns any data.
+    struct Stalled;
+    impl tokio::io::AsyncRead for Stalled {
+        fn poll_read(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+            _: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Pending
+        }
+    }
+
+    let mut sz = AsyncSevenZWriter::new(Cursor::new(Vec::new()));
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "stalled.txt".to_string();
+    entry.has_stream = true;
+    tokio::select! {
+        biased;
+        _ = sz.push_archive_entry(entry, Some(Stalled)) => panic!("the reader never returns"),
+        _ = std::future::ready(()) => {}
+    }
+
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "a.txt".to_string();
+    entry.has_stream = true;
+    let result = sz.push_archive_entry(entry, Some(&b"a"[..])).await;
+    assert!(matches!(result, Err(Error::Other(_))));
+    assert!(sz.finish().await.is_err());
+}
@@ -159,3 +159,699 @@ fn compress_one_file_with_random_content_encrypted() {
         assert_eq!(std::fs::read_to_string(&decompress_file).unwrap(), content);
     }
 }
+
+#[cfg(feature = "compress")]
+#[test]
+fn compress_executable_with_bcj_filters() {
+    let content = std::fs::read("tests/resources/decompress_x86.exe").unwrap();
+    let temp_dir = tempdir().unwrap();
+    for filter in [
+        SevenZMethod::BCJ_X86_FILTER,
+        SevenZMethod::BCJ_ARM_FILTER,
+        SevenZMethod::BCJ_ARM_THUMB_FILTER,
+        SevenZMethod::BCJ_PPC_FILTER,
+        SevenZMethod::BCJ_SPARC_FILTER,
+    ] {
+        let dest = temp_dir.path().join(format!("{}.7z", filter.name()));
+        let mut sz = SevenZWriter::create(&dest).unwrap();
+        sz.set_content_methods(vec![filter.into(), SevenZMethod::LZMA2.into()]);
+        let mut entry = SevenZArchiveEntry::new();
+        entry.name = "decompress_x86.exe".to_string();
+        sz.push_archive_entry(entry, Some(content.as_slice()))
+            .unwrap();
+        sz.finish().unwrap();
+
+        // the filter is applied before LZMA2, so its coder is unpacked last
+        let archive = Archive::open(&dest).unwrap();
+        let coders = &archive.folders[0].coders;
+        assert_eq!(coders.len(), 2);
+        assert_eq!(coders[0].decompression_method_id(), SevenZMethod::ID_LZMA2);
+        assert_eq!(coders[1].decompression_method_id(), filter.id());
+
+        let decompress_dest = temp_dir.path().join(filter.name());
+        decompress_file(&dest, &decompress_dest).expect("decompress ok");
+        assert_eq!(
+            std::fs::read(decompress_dest.join("decompress_x86.exe")).unwrap(),
+            content
+        );
+    }
+
+    // IA64 can only be decoded
+    assert!(!SevenZMethod::BCJ_IA64_FILTER.is_filter());
+    let mut sz = SevenZWriter::new(std::io::Cursor::new(Vec::new())).unwrap();
+    sz.set_content_methods(vec![
+        SevenZMethod::BCJ_IA64_FILTER.into(),
+        SevenZMethod::LZMA2.into(),
+    ]);
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "decompress_x86.exe".to_string();
+    let result = sz.push_archive_entry(entry, Some(content.as_slice()));
+    assert!(matches!(
+        result,
+        Err(sevenz_rust::Error::UnsupportedCompressionMethod(_))
+    ));
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn compress_with_delta_filter() {
+    let content = (0..100_000u32)
+        .flat_map(|i| (i / 7).to_le_bytes())
+        .collect::<Vec<_>>();
+    let temp_dir = tempdir().unwrap();
+    let dest = temp_dir.path().join("delta.7z");
+    let mut sz = SevenZWriter::create(&dest).unwrap();
+    sz.set_content_methods(vec![
+        SevenZMethod::LZMA2.into(),
+        SevenZMethodConfiguration::new(SevenZMethod::DELTA_FILTER).with_options(4.into()),
+    ]);
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "samples.raw".to_string();
+    sz.push_archive_entry(entry, Some(content.as_slice()))
+        .unwrap();
+    sz.finish().unwrap();
+
+    let archive = Archive::open(&dest).unwrap();
+    let coders = &archive.folders[0].coders;
+    assert_eq!(coders[1].decompression_method_id(), SevenZMethod::ID_DELTA);
+    assert_eq!(coders[1].properties, [3]);
+
+    let decompress_dest = temp_dir.path().join("decompress");
+    decompress_file(&dest, &decompress_dest).expect("decompress ok");
+    assert_eq!(
+        std::fs::read(decompress_dest.join("samples.raw")).unwrap(),
+        content
+    );
+
+    let mut sz = SevenZWriter::create(temp_dir.path().join("bad.7z")).unwrap();
+    sz.set_content_methods(vec![
+        SevenZMethod::LZMA2.into(),
+        SevenZMethodConfiguration::new(SevenZMethod::DELTA_FILTER).with_options(257.into()),
+    ]);
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "samples.raw".to_string();
+    assert!(sz
+        .push_archive_entry(entry.clone(), Some(content.as_slice()))
+        .is_err());
+    // options other than a distance
+    sz.set_content_methods(vec![
+        SevenZMethod::LZMA2.into(),
+        SevenZMethodConfiguration::new(SevenZMethod::DELTA_FILTER)
+            .with_options(lzma::LZMA2Options::default().into()),
+    ]);
+    assert!(sz
+        .push_archive_entry(entry, Some(content.as_slice()))
+        .is_err());
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn compress_executable_with_bcj2() {
+    let exe = std::fs::read("tests/resources/decompress_x86.exe").unwrap();
+    let temp_dir = tempdir().unwrap();
+    for (i, content) in [
+        exe.clone(),
+        vec![0x0F, 0x85, 0xE8, 0x00, 0xE8, 0x01, 0x02, 0x03, 0xE9],
+        Vec::new(),
+    ]
+    .iter()
+    .enumerate()
+    {
+        for main_methods in [vec![], vec![SevenZMethod::LZMA2.into()]] {
+            let dest = temp_dir
+                .path()
+                .join(format!("bcj2_{}_{}.7z", i, main_methods.len()));
+            let mut sz = SevenZWriter::create(&dest).unwrap();
+            let mut methods: Vec<SevenZMethodConfiguration> = main_methods;
+            methods.push(SevenZMethod::BCJ2_FILTER.into());
+            sz.set_content_methods(methods);
+            let mut entry = SevenZArchiveEntry::new();
+            entry.name = "decompress_x86.exe".to_string();
+            sz.push_archive_entry(entry, Some(content.as_slice()))
+                .unwrap();
+            sz.finish().unwrap();
+
+            let archive = Archive::open(&dest).unwrap();
+            if !content.is_empty() {
+                let folder = &archive.folders[0];
+                assert_eq!(folder.packed_streams.len(), 4);
+                assert_eq!(
+                    folder.coders.last().unwrap().decompression_method_id(),
+                    SevenZMethod::ID_BCJ2
+                );
+            }
+            let mut sz = SevenZReader::open(&dest, Password::empty()).unwrap();
+            let mut decompressed = Vec::new();
+            sz.for_each_entries(|_, reader| {
+                std::io::copy(reader, &mut decompressed)?;
+                Ok(true)
+            })
+            .unwrap();
+            assert_eq!(&decompressed, content);
+        }
+    }
+}
+
+#[cfg(feature = "compress")]
+fn compress_with_method(method: SevenZMethodConfiguration) -> Archive {
+    let content = (0..10000)
+        .map(|i| format!("line {} with some content\n", i))
+        .collect::<String>();
+    let temp_dir = tempdir().unwrap();
+    let dest = temp_dir.path().join("dest.7z");
+    let mut sz = SevenZWriter::create(&dest).unwrap();
+    sz.set_content_methods(vec![method]);
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "file.txt".to_string();
+    sz.push_archive_entry(entry, Some(content.as_bytes()))
+        .unwrap();
+    sz.finish().unwrap();
+
+    let decompress_dest = temp_dir.path().join("decompress");
+    decompress_file(&dest, &decompress_dest).expect("decompress ok");
+    assert_eq!(
+        std::fs::read_to_string(decompress_dest.join("file.txt")).unwrap(),
+        content
+    );
+    Archive::open(&dest).unwrap()
+}
+
+#[cfg(all(feature = "compress", feature = "zstd"))]
+#[test]
+fn compress_with_zstd() {
+    let archive = compress_with_method(
+        SevenZMethodConfiguration::new(SevenZMethod::ZSTD).with_options(19.into()),
+    );
+    let coder = &archive.folders[0].coders[0];
+    assert_eq!(coder.decompression_method_id(), SevenZMethod::ID_ZSTD);
+    assert_eq!(coder.properties.len(), 5);
+    assert_eq!(coder.properties[2], 19);
+}
+
+#[cfg(all(feature = "compress", feature = "bzip2"))]
+#[test]
+fn compress_with_bzip2() {
+    let archive = compress_with_method(
+        SevenZMethodConfiguration::new(SevenZMethod::BZIP2).with_options(9.into()),
+    );
+    let coder = &archive.folders[0].coders[0];
+    assert_eq!(coder.decompression_method_id(), SevenZMethod::ID_BZIP2);
+    assert!(coder.properties.is_empty());
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn compress_non_solid_with_multiple_threads() {
+    let temp_dir = tempdir().unwrap();
+    let folder = temp_dir.path().join("folder");
+    std::fs::create_dir(&folder).unwrap();
+    let mut contents = Vec::new();
+    for i in 0..10 {
+        let content = format!("file{i} ").repeat(1000 * (i + 1));
+        std::fs::write(folder.join(format!("file{i}.txt")), &content).unwrap();
+        contents.push(content);
+    }
+
+    let compress = |num_threads| {
+        let dest = temp_dir.path().join(format!("folder{num_threads}.7z"));
+        let mut sz = SevenZWriter::create(&dest).unwrap();
+        sz.set_num_threads(num_threads);
+        sz.push_source_path_non_solid(&folder, |_| true).unwrap();
+        sz.finish().unwrap();
+        let archive = Archive::open(&dest).unwrap();
+        let pack_size: u64 = archive.pack_sizes.iter().sum();
+        let packed = std::fs::read(&dest).unwrap()[32..32 + pack_size as usize].to_vec();
+        (dest, archive, packed)
+    };
+    let (_, serial, serial_packed) = compress(1);
+    let (dest, parallel, parallel_packed) = compress(4);
+    // access times may differ, but entries and packed streams are in the same order
+    assert_eq!(serial_packed, parallel_packed);
+    // with fewer threads, most entries wait for the writer to catch up
+    assert_eq!(serial_packed, compress(2).2);
+    assert_eq!(serial.pack_sizes, parallel.pack_sizes);
+    assert_eq!(parallel.folders.len(), contents.len());
+    let names = |archive: &Archive| {
+        let names = archive.files.iter().map(|f| f.name().to_string());
+        names.collect::<Vec<_>>()
+    };
+    assert_eq!(names(&serial), names(&parallel));
+
+    let decompress_dest = temp_dir.path().join("decompress");
+    decompress_file(&dest, &decompress_dest).expect("decompress ok");
+    for (i, content) in contents.iter().enumerate() {
+        let path = decompress_dest.join(format!("file{i}.txt"));
+        assert_eq!(&std::fs::read_to_string(path).unwrap(), content);
+    }
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn compress_non_solid_with_failing_entry() {
+    use std::io::{Cursor, Read};
+
+    /// Fails on the first read.
+    struct FailingReader;
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "broken source",
+            ))
+        }
+    }
+
+    let content = |i: usize| format!("file{i} ").repeat(1000 * (i + 1));
+    for failing in [0, 5, 19] {
+        let entries = (0..20)
+            .map(|i| {
+                let mut entry = SevenZArchiveEntry::new();
+                entry.name = format!("file{i}.txt");
+                let reader: Box<dyn Read + Send> = if i == failing {
+                    Box::new(FailingReader)
+                } else {
+                    Box::new(Cursor::new(content(i)))
+                };
+                (entry, Some(reader))
+            })
+            .collect();
+        let mut sz = SevenZWriter::new(Cursor::new(Vec::new())).unwrap();
+        sz.set_num_threads(2);
+        assert!(sz.push_archive_entries_non_solid(entries).is_err());
+        let data = sz.finish().unwrap().into_inner();
+
+        let len = data.len() as u64;
+        let mut sz = SevenZReader::new(Cursor::new(data), len, Password::empty()).unwrap();
+        let mut names = Vec::new();
+        sz.for_each_entries(|entry, reader| {
+            let mut buf = String::new();
+            reader.read_to_string(&mut buf)?;
+            assert_eq!(buf, content(names.len()));
+            names.push(entry.name().to_string());
+            Ok(true)
+        })
+        .unwrap();
+        let expected = (0..failing).map(|i| format!("file{i}.txt"));
+        assert_eq!(names, expected.collect::<Vec<_>>());
+    }
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn compress_lzma2_in_blocks() {
+    use sevenz_rust::lzma::{
+        CountingWriter, LZMA2MtOptions, LZMA2MtWriter, LZMA2Options, LZMA2Reader,
+    };
+    use std::io::{Read, Write};
+
+    let mut options = LZMA2MtOptions::new(LZMA2Options::with_preset(1), 1);
+    options.block_size = Some(64 << 10);
+    let archive = compress_with_method(options.clone().into());
+    let coder = &archive.folders[0].coders[0];
+    assert_eq!(coder.decompression_method_id(), SevenZMethod::ID_LZMA2);
+    options.threads = 4;
+    let threaded = compress_with_method(options.clone().into());
+    assert_eq!(threaded.pack_sizes, archive.pack_sizes);
+
+    // the output doesn't depend on the number of threads
+    let content = (0..10000)
+        .map(|i| format!("line {} with some content\n", i))
+        .collect::<String>();
+    let compress = |threads| {
+        let mut out = Vec::new();
+        let options = LZMA2MtOptions {
+            threads,
+            ..options.clone()
+        };
+        let mut w = LZMA2MtWriter::new(CountingWriter::new(&mut out), &options);
+        w.write_all(content.as_bytes()).unwrap();
+        w.finish().unwrap();
+        drop(w);
+        out
+    };
+    let compressed = compress(4);
+    assert_eq!(compressed, compress(1));
+    let mut decompressed = String::new();
+    LZMA2Reader::new(&compressed[..], options.lzma2.dict_size, None)
+        .read_to_string(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, content);
+}
+
+#[cfg(all(feature = "compress", unix))]
+#[test]
+fn compress_keeps_unix_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempdir().unwrap();
+    let folder = temp_dir.path().join("folder");
+    std::fs::create_dir(&folder).unwrap();
+    let script = folder.join("run.sh");
+    std::fs::write(&script, "#!/bin/sh\necho hello\n").unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o754)).unwrap();
+    let text = folder.join("text.txt");
+    std::fs::write(&text, "text").unwrap();
+    std::fs::set_permissions(&text, std::fs::Permissions::from_mode(0o640)).unwrap();
+    let dest = temp_dir.path().join("folder.7z");
+    compress_to_path(&folder, &dest).expect("compress ok");
+
+    let archive = Archive::open(&dest).unwrap();
+    let entry = |name: &str| archive.files.iter().find(|f| f.name() == name).unwrap();
+    assert_eq!(entry("run.sh").unix_mode(), Some(0o100754));
+    assert_eq!(entry("run.sh").unix_permissions(), Some(0o754));
+    assert_eq!(entry("text.txt").unix_permissions(), Some(0o640));
+    assert!(!entry("text.txt").is_read_only());
+
+    let decompress_dest = temp_dir.path().join("decompress");
+    decompress_file(&dest, &decompress_dest).expect("decompress ok");
+    let mode = |name: &str| {
+        let meta = std::fs::metadata(decompress_dest.join(name)).unwrap();
+        meta.permissions().mode() & 0o7777
+    };
+    assert_eq!(mode("run.sh"), 0o754);
+    assert_eq!(mode("text.txt"), 0o640);
+}
+
+#[test]
+fn unix_mode_in_windows_attributes() {
+    let mut entry = SevenZArchiveEntry::new();
+    assert_eq!(entry.unix_mode(), None);
+    entry.has_windows_attributes = true;
+    entry.windows_attributes = SevenZArchiveEntry::FILE_ATTRIBUTE_READONLY;
+    assert_eq!(entry.unix_mode(), None);
+    assert!(entry.is_read_only());
+
+    entry.set_unix_mode(0o100755);
+    assert_eq!(
+        entry.windows_attributes(),
+        0x81ED_8001,
+        "mode in the high 16 bits with the unix extension flag"
+    );
+    assert_eq!(entry.unix_mode(), Some(0o100755));
+    assert_eq!(entry.unix_permissions(), Some(0o755));
+    assert!(entry.is_read_only());
+}
+
+#[cfg(all(feature = "compress", unix))]
+#[test]
+fn compress_and_restore_symlinks() {
+    let temp_dir = tempdir().unwrap();
+    let folder = temp_dir.path().join("folder");
+    std::fs::create_dir_all(folder.join("sub")).unwrap();
+    std::fs::write(folder.join("sub/file.txt"), "content").unwrap();
+    std::os::unix::fs::symlink("sub/file.txt", folder.join("file_link")).unwrap();
+    std::os::unix::fs::symlink("sub", folder.join("dir_link")).unwrap();
+    std::os::unix::fs::symlink("../file.txt", folder.join("sub/up_link")).unwrap();
+
+    let dest = temp_dir.path().join("folder.7z");
+    compress_to_path(&folder, &dest).expect("compress ok");
+    let archive = Archive::open(&dest).unwrap();
+    let entry = |name: &str| archive.files.iter().find(|f| f.name() == name).unwrap();
+    assert!(entry("file_link").is_symlink());
+    assert_eq!(entry("file_link").size(), "sub/file.txt".len() as u64);
+    assert!(entry("dir_link").is_symlink());
+    assert!(!entry("dir_link").is_directory());
+    assert!(!entry("sub/file.txt").is_symlink());
+
+    let decompress_dest = temp_dir.path().join("decompress");
+    decompress_file(&dest, &decompress_dest).expect("decompress ok");
+    let link = |name: &str| std::fs::read_link(decompress_dest.join(name)).unwrap();
+    assert_eq!(link("file_link"), std::path::Path::new("sub/file.txt"));
+    assert_eq!(link("dir_link"), std::path::Path::new("sub"));
+    assert_eq!(link("sub/up_link"), std::path::Path::new("../file.txt"));
+    assert_eq!(
+        std::fs::read_to_string(decompress_dest.join("file_link")).unwrap(),
+        "content"
+    );
+
+    let dest = temp_dir.path().join("solid.7z");
+    let mut sz = SevenZWriter::create(&dest).unwrap();
+    sz.set_follow_symlinks(true);
+    sz.push_source_path(&folder, |_| true).unwrap();
+    sz.finish().unwrap();
+    let archive = Archive::open(&dest).unwrap();
+    assert!(archive.files.iter().all(|f| !f.is_symlink()));
+    assert!(archive
+        .files
+        .iter()
+        .any(|f| f.name() == "dir_link/file.txt"));
+
+    // links back to a containing directory are not followed forever
+    std::os::unix::fs::symlink("..", folder.join("sub/loop")).unwrap();
+    for solid in [true, false] {
+        let mut sz = SevenZWriter::create(&dest).unwrap();
+        sz.set_follow_symlinks(true);
+        if solid {
+            sz.push_source_path(&folder, |_| true).unwrap();
+        } else {
+            sz.push_source_path_non_solid(&folder, |_| true).unwrap();
+        }
+        sz.finish().unwrap();
+        let archive = Archive::open(&dest).unwrap();
+        assert!(archive.files.iter().all(|f| !f.name().contains("loop")));
+    }
+    let looped = temp_dir.path().join("looped.7z");
+    sevenz_rust::compress_to_path(&folder, &looped).unwrap();
+    let archive = Archive::open(&looped).unwrap();
+    assert!(archive
+        .files
+        .iter()
+        .any(|f| f.name() == "sub/loop" && f.is_symlink()));
+    std::fs::remove_file(folder.join("sub/loop")).unwrap();
+
+    let mut sz = SevenZWriter::create(&dest).unwrap();
+    sz.push_source_path_non_solid(&folder, |_| true).unwrap();
+    sz.finish().unwrap();
+    let archive = Archive::open(&dest).unwrap();
+    assert_eq!(archive.files.iter().filter(|f| f.is_symlink()).count(), 3);
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn compress_with_stub() {
+    use std::io::Cursor;
+
+    let stub = b"#!/bin/sh\necho self-extracting stub\nexit 0\n".repeat(100);
+    let mut sz = SevenZWriter::new_with_stub(Cursor::new(Vec::new()), &stub).unwrap();
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "file.txt".to_string();
+    sz.push_archive_entry(entry, Some("content".as_bytes()))
+        .unwrap();
+    let data = sz.finish().unwrap().into_inner();
+    assert!(data.starts_with(&stub));
+
+    let options = ReaderOptions::default().with_signature_search_limit(1 << 20);
+    let len = data.len() as u64;
+    let mut sz =
+        SevenZReader::new_with_options(Cursor::new(data), len, Password::empty(), options).unwrap();
+    assert_eq!(sz.archive().signature_offset, stub.len() as u64);
+    let mut content = String::new();
+    sz.for_each_entries(|_, reader| {
+        reader.read_to_string(&mut content)?;
+        Ok(true)
+    })
+    .unwrap();
+    assert_eq!(content, "content");
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn compress_to_non_seekable_sink() {
+    use std::io::{Cursor, Write};
+
+    /// A sink that only implements [Write], like a pipe.
+    struct Pipe(Vec<u8>);
+    impl Write for Pipe {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let temp_dir = tempdir().unwrap();
+    let folder = temp_dir.path().join("folder");
+    std::fs::create_dir(&folder).unwrap();
+    std::fs::write(folder.join("a.txt"), "content of a".repeat(1000)).unwrap();
+    std::fs::write(folder.join("b.txt"), "b").unwrap();
+
+    let mut sz = SevenZWriter::new_streaming(Pipe(Vec::new())).unwrap();
+    sz.push_source_path(&folder, |_| true).unwrap();
+    let in_memory = sz.finish_streaming().unwrap().0;
+
+    // a reused spool file longer than the archive
+    let mut spool = tempfile().unwrap();
+    spool.write_all(&[0xff; 1 << 20]).unwrap();
+    let mut sz = SevenZWriter::new_streaming_with_spool(Pipe(Vec::new()), spool).unwrap();
+    sz.push_source_path(&folder, |_| true).unwrap();
+    let spooled = sz.finish_streaming().unwrap().0;
+    assert!(spooled.len() < 1 << 20);
+
+    // the plain `finish` keeps the archive in the spool until it is copied to the sink
+    let mut sz = SevenZWriter::new_streaming(Pipe(Vec::new())).unwrap();
+    sz.push_source_path(&folder, |_| true).unwrap();
+    let finished = sz.finish().unwrap().into_sink().unwrap().0;
+
+    for data in [in_memory, spooled, finished] {
+        let len = data.len() as u64;
+        let mut sz = SevenZReader::new(Cursor::new(data), len, Password::empty()).unwrap();
+        let mut contents = Vec::new();
+        sz.for_each_entries(|entry, reader| {
+            let mut content = String::new();
+            reader.read_to_string(&mut content)?;
+            contents.push((entry.name().to_string(), content));
+            Ok(true)
+        })
+        .unwrap();
+        contents.sort();
+        assert_eq!(
+            contents,
+            [
+                ("a.txt".to_string(), "content of a".repeat(1000)),
+                ("b.txt".to_string(), "b".to_string())
+            ]
+        );
+    }
+}
+
+/// A progress event as `(event, name or "", unpacked, packed)`.
+#[cfg(feature = "compress")]
+type ProgressEvent = (&'static str, String, u64, u64);
+
+/// Records the progress events.
+#[cfg(feature = "compress")]
+#[derive(Clone, Default)]
+struct RecordProgress(std::sync::Arc<std::sync::Mutex<Vec<ProgressEvent>>>);
+
+#[cfg(feature = "compress")]
+impl Progress for RecordProgress {
+    fn entry_started(&mut self, entry: &SevenZArchiveEntry) {
+        let event = ("started", entry.name().to_string(), 0, 0);
+        self.0.lock().unwrap().push(event);
+    }
+    fn entry_finished(&mut self, entry: &SevenZArchiveEntry) {
+        let event = ("finished", entry.name().to_string(), 0, 0);
+        self.0.lock().unwrap().push(event);
+    }
+    fn bytes_processed(&mut self, unpacked: u64, packed: u64) {
+        let event = ("bytes", String::new(), unpacked, packed);
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn compress_with_progress() {
+    let temp_dir = tempdir().unwrap();
+    let folder = temp_dir.path().join("folder");
+    std::fs::create_dir(&folder).unwrap();
+    std::fs::write(folder.join("a.txt"), "content of a".repeat(10_000)).unwrap();
+    std::fs::write(folder.join("b.txt"), "b").unwrap();
+
+    for (solid, num_threads) in [(true, 1), (false, 1), (false, 2)] {
+        let dest = temp_dir.path().join("folder.7z");
+        let progress = RecordProgress::default();
+        let mut sz = SevenZWriter::create(&dest).unwrap();
+        sz.set_progress(progress.clone());
+        sz.set_num_threads(num_threads);
+        if solid {
+            sz.push_source_path(&folder, |_| true).unwrap();
+        } else {
+            sz.push_source_path_non_solid(&folder, |_| true).unwrap();
+        }
+        sz.finish().unwrap();
+
+        let events = progress.0.lock().unwrap();
+        let names = |kind| {
+            let names = events.iter().filter(|e| e.0 == kind).map(|e| e.1.clone());
+            let mut names = names.collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+        assert_eq!(names("started"), ["a.txt", "b.txt"]);
+        assert_eq!(names("finished"), ["a.txt", "b.txt"]);
+        let unpacked: u64 = events.iter().map(|e| e.2).sum();
+        let packed: u64 = events.iter().map(|e| e.3).sum();
+        assert_eq!(unpacked, 12 * 10_000 + 1);
+        let archive = Archive::open(&dest).unwrap();
+        assert_eq!(packed, archive.pack_sizes.iter().sum::<u64>());
+    }
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn cancel_compression() {
+    use std::io::{Cursor, Read, Seek, Write};
+
+    /// Reads 1 MiB of incompressible data, then cancels the token.
+    struct CancellingReader(CancellationToken, usize, u32);
+    impl Read for CancellingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.1 == 0 {
+                self.0.cancel();
+            }
+            for b in buf.iter_mut() {
+                self.2 = self.2.wrapping_mul(1103515245).wrapping_add(12345);
+                *b = (self.2 >> 16) as u8;
+            }
+            self.1 = self.1.saturating_sub(buf.len());
+            Ok(buf.len())
+        }
+    }
+
+    fn push_entries<W: Write + Seek>(sz: &mut SevenZWriter<W>) {
+        let entry = |name: &str| {
+            let mut entry = SevenZArchiveEntry::new();
+            entry.name = name.to_string();
+            entry
+        };
+        let token = CancellationToken::new();
+        sz.set_cancellation_token(token.clone());
+        sz.push_archive_entry(entry("kept.txt"), Some("kept".as_bytes()))
+            .unwrap();
+        let reader = CancellingReader(token, 1 << 20, 1);
+        let result = sz.push_archive_entry(entry("cancelled.txt"), Some(reader));
+        assert!(matches!(result, Err(Error::Cancelled)));
+        let result = sz.push_archive_entry(entry("after.txt"), Some("after".as_bytes()));
+        assert!(matches!(result, Err(Error::Cancelled)));
+    }
+    let check = |data: Vec<u8>| {
+        let len = data.len() as u64;
+        let mut sz = SevenZReader::new(Cursor::new(data), len, Password::empty()).unwrap();
+        let mut contents = Vec::new();
+        sz.for_each_entries(|entry, reader| {
+            let mut content = String::new();
+            reader.read_to_string(&mut content)?;
+            contents.push((entry.name().to_string(), content));
+            Ok(true)
+        })
+        .unwrap();
+        assert_eq!(contents, [("kept.txt".to_string(), "kept".to_string())]);
+    };
+    // the archive ends with its header, without the data of the cancelled entry
+    let header_end = |data: &[u8]| {
+        let offset = u64::from_le_bytes(data[12..20].try_into().unwrap());
+        let size = u64::from_le_bytes(data[20..28].try_into().unwrap());
+        32 + offset + size
+    };
+
+    let mut sz = SevenZWriter::new(Cursor::new(Vec::new())).unwrap();
+    push_entries(&mut sz);
+    check(sz.finish().unwrap().into_inner());
+
+    let temp_dir = tempdir().unwrap();
+    let dest = temp_dir.path().join("cancelled.7z");
+    let mut sz = SevenZWriter::create(&dest).unwrap();
+    push_entries(&mut sz);
+    sz.finish().unwrap();
+    let data = std::fs::read(&dest).unwrap();
+    assert_eq!(data.len() as u64, header_end(&data));
+    check(data);
+
+    let mut sz = SevenZWriter::new_streaming(Vec::new()).unwrap();
+    push_entries(&mut sz);
+    let data = sz.finish_streaming().unwrap();
+    assert_eq!(data.len() as u64, header_end(&data));
+    check(data);
+}
@@ -1,5 +1,8 @@
+#[cfg(feature = "aes256")]
 use sevenz_rust::*;
+#[cfg(feature = "aes256")]
 use std::{fs::read_to_string, path::PathBuf};
+#[cfg(feature = "aes256")]
 use tempfile::tempdir;
 
 #[cfg(feature = "aes256")]
@@ -17,3 +20,20 @@ fn test_decompress_file_with_password() {
         .unwrap()
         .starts_with("7z is the new archive format, providing high compression ratio."))
 }
+
+#[cfg(feature = "aes256")]
+#[test]
+fn test_decompress_parallel_with_password() {
+    let mut source_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    source_file.push("tests/resources/encrypted.7z");
+    let temp_dir = tempdir().unwrap();
+    let target = temp_dir.path().to_path_buf();
+    let r = decompress_parallel_with_password(&source_file, &target, 2, "sevenz-rust".into());
+    assert!(r.is_ok());
+    assert!(read_to_string(target.join("encripted/7zFormat.txt"))
+        .unwrap()
+        .starts_with("7z is the new archive format, providing high compression ratio."));
+
+    let wrong = temp_dir.path().join("wrong");
+    assert!(decompress_parallel_with_password(&source_file, wrong, 2, "wrong".into()).is_err());
+}
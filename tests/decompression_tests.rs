@@ -5,7 +5,7 @@ use std::{
 
 use tempfile::tempdir;
 
-use sevenz_rust::{decompress_file, Archive, BlockDecoder};
+use sevenz_rust::{decompress_file, Archive, BlockDecoder, Password, SevenZReader};
 
 #[test]
 fn decompress_single_empty_file_unencoded_header() {
@@ -137,6 +137,34 @@ fn decompress_bzip2_file() {
     assert_eq!(read_to_string(foo_path).unwrap(), "bar\n");
 }
 
+#[cfg(any(feature = "deflate", feature = "deflate64"))]
+fn decompress_deflate_resource(archive: &str, name: &str) {
+    let mut source_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    source_file.push("tests/resources");
+    source_file.push(archive);
+    let temp_dir = tempdir().unwrap();
+    let target = temp_dir.path().to_path_buf();
+
+    decompress_file(source_file, &target).unwrap();
+
+    let expected = (0..200)
+        .map(|i| format!("line {}: the quick brown fox jumps over the lazy dog\n", i))
+        .collect::<String>();
+    assert_eq!(read_to_string(target.join(name)).unwrap(), expected);
+}
+
+#[cfg(feature = "deflate")]
+#[test]
+fn decompress_deflate_file() {
+    decompress_deflate_resource("deflate.7z", "deflate.txt");
+}
+
+#[cfg(feature = "deflate64")]
+#[test]
+fn decompress_deflate64_file() {
+    decompress_deflate_resource("deflate64.7z", "deflate64.txt");
+}
+
 #[test]
 fn test_bcj2() {
     let mut file = File::open("tests/resources/7za433_7zip_lzma2_bcj2.7z").unwrap();
@@ -180,3 +208,599 @@ fn test_entry_compressed_size() {
         }
     }
 }
+
+#[test]
+fn read_entry_from_multi_file_archive() {
+    let mut sz = SevenZReader::open(
+        "tests/resources/two_files_with_content_lzma.7z",
+        Password::empty(),
+    )
+    .unwrap();
+
+    let file2 = std::io::read_to_string(sz.read_entry("file2.txt").unwrap()).unwrap();
+    assert_eq!(file2, "file two content\n");
+    let file1 = std::io::read_to_string(sz.read_entry_at(0).unwrap()).unwrap();
+    assert_eq!(file1, "file one content\n");
+    assert!(sz.read_entry("missing.txt").is_err());
+}
+
+#[test]
+fn decompress_blocks_in_parallel() {
+    let source_file = "tests/resources/7za433_7zip_lzma2_bcj2.7z";
+    let temp_dir = tempdir().unwrap();
+    let serial = temp_dir.path().join("serial");
+    let parallel = temp_dir.path().join("parallel");
+    decompress_file(source_file, &serial).unwrap();
+    sevenz_rust::decompress_parallel(source_file, &parallel, 4).unwrap();
+
+    let archive = Archive::open(source_file).unwrap();
+    assert!(archive.folders.len() > 1);
+    for entry in archive.files.iter().filter(|f| !f.is_directory()) {
+        assert_eq!(
+            read(parallel.join(entry.name())).unwrap(),
+            read(serial.join(entry.name())).unwrap()
+        );
+    }
+}
+
+#[test]
+fn parallel_decompression_returns_first_error() {
+    let source_file = "tests/resources/7za433_7zip_lzma2_bcj2.7z";
+    let sz = SevenZReader::open(source_file, Password::empty()).unwrap();
+    let result = sz.par_for_each_entries(
+        4,
+        || File::open(source_file),
+        |entry, _| Err(sevenz_rust::Error::other(entry.name().to_string())),
+    );
+    assert!(matches!(result, Err(sevenz_rust::Error::Other(_))));
+}
+
+#[test]
+fn decompress_shared_in_memory_source() {
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let data = read("tests/resources/7za433_7zip_lzma2_bcj2.7z").unwrap();
+    let len = data.len() as u64;
+    let sz = SevenZReader::new(std::io::Cursor::new(data), len, Password::empty()).unwrap();
+    assert_send_sync(&sz);
+
+    let sizes = std::sync::Mutex::new(Vec::new());
+    sz.par_for_each_entries_shared(4, |entry, reader| {
+        let size = std::io::copy(reader, &mut std::io::sink())?;
+        sizes.lock().unwrap().push((entry.name().to_string(), size));
+        Ok(true)
+    })
+    .unwrap();
+    let mut sizes = sizes.into_inner().unwrap();
+    sizes.sort();
+    let mut expected = sz
+        .archive()
+        .files
+        .iter()
+        .map(|f| (f.name().to_string(), f.size()))
+        .collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(sizes, expected);
+}
+
+#[test]
+fn decompress_with_memory_limit() {
+    let source_file = "tests/resources/7za433_7zip_lzma2_bcj2.7z";
+    let options = sevenz_rust::ReaderOptions::default().with_mem_limit_kb(1024);
+    let mut sz = SevenZReader::open_with_options(source_file, Password::empty(), options).unwrap();
+    let result = sz.for_each_entries(|_, reader| {
+        std::io::copy(reader, &mut std::io::sink())?;
+        Ok(true)
+    });
+    assert!(matches!(
+        result,
+        Err(sevenz_rust::Error::MaxMemLimited { max_kb: 1024, .. })
+    ));
+
+    let options = sevenz_rust::ReaderOptions::default().with_mem_limit_kb(16 * 1024);
+    let mut sz = SevenZReader::open_with_options(source_file, Password::empty(), options).unwrap();
+    sz.for_each_entries(|_, reader| {
+        std::io::copy(reader, &mut std::io::sink())?;
+        Ok(true)
+    })
+    .unwrap();
+
+    let mut file = File::open(source_file).unwrap();
+    let len = file.metadata().unwrap().len();
+    let options = sevenz_rust::ReaderOptions::default().with_mem_limit_kb(0);
+    let result = Archive::read_with_options(&mut file, len, &[], options);
+    assert!(matches!(
+        result,
+        Err(sevenz_rust::Error::MaxMemLimited { max_kb: 0, .. })
+    ));
+}
+
+fn test_archive(data: Vec<u8>) -> sevenz_rust::TestReport {
+    let len = data.len() as u64;
+    let mut sz = SevenZReader::new(std::io::Cursor::new(data), len, Password::empty()).unwrap();
+    sz.test().unwrap()
+}
+
+#[test]
+fn test_archive_reports_each_entry() {
+    use sevenz_rust::TestStatus;
+
+    let data = read("tests/resources/7za433_7zip_lzma2_bcj2.7z").unwrap();
+    assert!(test_archive(data.clone()).is_ok());
+
+    let mut corrupt = data;
+    corrupt[42] ^= 0x55;
+    let report = test_archive(corrupt);
+    assert!(!report.is_ok());
+    assert_eq!(report.failures().count(), 2);
+    assert!(report.entries[..2]
+        .iter()
+        .all(|e| matches!(e.status, TestStatus::DataError(_))));
+    assert!(report.entries[2..]
+        .iter()
+        .all(|e| e.status == TestStatus::Ok));
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn test_archive_verifies_pack_crcs() {
+    use sevenz_rust::{SevenZArchiveEntry, SevenZWriter, TestStatus};
+
+    let mut sz = SevenZWriter::new(std::io::Cursor::new(Vec::new())).unwrap();
+    for name in ["a.txt", "b.txt"] {
+        let mut entry = SevenZArchiveEntry::new();
+        entry.name = name.to_string();
+        let content = format!("content of {}", name).repeat(100);
+        sz.push_archive_entry(entry, Some(content.as_bytes()))
+            .unwrap();
+    }
+    let mut data = sz.finish().unwrap().into_inner();
+    assert!(test_archive(data.clone()).is_ok());
+
+    data[40] ^= 0x55;
+    let report = test_archive(data);
+    assert_eq!(report.entries[0].status, TestStatus::CrcMismatch);
+    assert_eq!(report.entries[1].status, TestStatus::Ok);
+}
+
+#[test]
+fn decompress_with_extract_limits() {
+    use sevenz_rust::{Error, ExtractLimit, ReaderOptions};
+
+    let source_file = "tests/resources/7za433_7zip_lzma2_bcj2.7z";
+    let archive = Archive::open(source_file).unwrap();
+    let total_size: u64 = archive.files.iter().map(|f| f.size).sum();
+    let max_size = archive.files.iter().map(|f| f.size).max().unwrap();
+    let read_all = |options: ReaderOptions| {
+        let mut sz = SevenZReader::open_with_options(source_file, Password::empty(), options)?;
+        sz.for_each_entries(|_, reader| {
+            // errors of the reader are swallowed, the limit must be reported anyway
+            let _ = std::io::copy(reader, &mut std::io::sink());
+            Ok(true)
+        })
+    };
+
+    let limits = [
+        (
+            ReaderOptions::default().with_max_entry_count(archive.files.len() - 1),
+            ExtractLimit::EntryCount(archive.files.len() - 1),
+        ),
+        (
+            ReaderOptions::default().with_max_entry_size(max_size - 1),
+            ExtractLimit::EntrySize(max_size - 1),
+        ),
+        (
+            ReaderOptions::default().with_max_unpacked_size(total_size - 1),
+            ExtractLimit::UnpackedSize(total_size - 1),
+        ),
+        (
+            ReaderOptions::default().with_max_compression_ratio(1),
+            ExtractLimit::CompressionRatio(1),
+        ),
+    ];
+    let read_all_with_cursor = |options: ReaderOptions| {
+        let mut sz = SevenZReader::open_with_options(source_file, Password::empty(), options)?;
+        let mut entries = sz.entries();
+        while let Some((_, mut reader)) = entries.next_entry()? {
+            let _ = std::io::copy(&mut reader, &mut std::io::sink());
+        }
+        Ok::<_, Error>(())
+    };
+    for (options, limit) in limits {
+        for result in [read_all(options), read_all_with_cursor(options)] {
+            match result {
+                Err(Error::LimitExceeded(l)) => assert_eq!(l, limit),
+                r => panic!("expected {limit:?}, got {r:?}"),
+            }
+        }
+    }
+
+    let options = ReaderOptions::default()
+        .with_max_entry_count(archive.files.len())
+        .with_max_entry_size(max_size)
+        .with_max_unpacked_size(total_size)
+        .with_max_compression_ratio(1000);
+    read_all(options).unwrap();
+    read_all_with_cursor(options).unwrap();
+
+    let temp_dir = tempdir().unwrap();
+    let options = ReaderOptions::default().with_max_unpacked_size(total_size - 1);
+    let result = sevenz_rust::decompress_file_with_options(
+        source_file,
+        temp_dir.path(),
+        Password::empty(),
+        options,
+    );
+    assert!(matches!(
+        result,
+        Err(Error::LimitExceeded(ExtractLimit::UnpackedSize(_)))
+    ));
+    let parallel = temp_dir.path().join("parallel");
+    let result = sevenz_rust::decompress_parallel_with_options(
+        source_file,
+        &parallel,
+        4,
+        Password::empty(),
+        options,
+    );
+    assert!(matches!(
+        result,
+        Err(Error::LimitExceeded(ExtractLimit::UnpackedSize(_)))
+    ));
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn reject_pack_size_beyond_archive() {
+    use sevenz_rust::{Error, ReaderOptions, SevenZArchiveEntry, SevenZWriter};
+    use std::io::Cursor;
+
+    let crc32 = |data: &[u8]| crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC).checksum(data);
+    let mut sz = SevenZWriter::new(Cursor::new(Vec::new())).unwrap();
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "file.txt".to_string();
+    sz.push_archive_entry(entry, Some("content".repeat(1000).as_bytes()))
+        .unwrap();
+    let mut data = sz.finish().unwrap().into_inner();
+
+    // the small header is not compressed: kHeader, kMainStreamsInfo, kPackInfo, pack pos 0,
+    // 1 pack stream, kSize and the size of the pack stream in one byte
+    let header_pos = 32 + u64::from_le_bytes(data[12..20].try_into().unwrap()) as usize;
+    assert_eq!(data[header_pos..header_pos + 6], [1, 4, 6, 0, 1, 9]);
+    assert!(data[header_pos + 6] < 0x80);
+    // forge a pack size of 2^60 so that the compression ratio would never be exceeded
+    let mut forged = vec![0xFF];
+    forged.extend_from_slice(&(1u64 << 60).to_le_bytes());
+    data.splice(header_pos + 6..header_pos + 7, forged);
+    let header_size = data.len() - header_pos;
+    data[20..28].copy_from_slice(&(header_size as u64).to_le_bytes());
+    let header_crc = crc32(&data[header_pos..]);
+    data[28..32].copy_from_slice(&header_crc.to_le_bytes());
+    let start_header_crc = crc32(&data[12..32]);
+    data[8..12].copy_from_slice(&start_header_crc.to_le_bytes());
+
+    let len = data.len() as u64;
+    let options = ReaderOptions::default().with_max_compression_ratio(2);
+    let result = SevenZReader::new_with_options(Cursor::new(data), len, Password::empty(), options);
+    assert!(matches!(result, Err(Error::Other(_))));
+}
+
+#[test]
+fn read_archive_after_stub() {
+    use sevenz_rust::{Error, ReaderOptions};
+    use std::io::Cursor;
+
+    let archive_bytes = read("tests/resources/two_files_with_content_lzma.7z").unwrap();
+    // the stub contains the signature bytes without a valid start header
+    let mut data = b"MZ stub 7z\xBC\xAF\x27\x1C\x00\x04 not an archive".repeat(5000);
+    let stub_len = data.len() as u64;
+    data.extend_from_slice(&archive_bytes);
+    let len = data.len() as u64;
+
+    let result = Archive::read(&mut Cursor::new(&data), len, &[]);
+    assert!(matches!(result, Err(Error::BadSignature(_))));
+
+    let options = ReaderOptions::default().with_signature_search_limit(stub_len - 1);
+    let result = Archive::read_with_options(&mut Cursor::new(&data), len, &[], options);
+    assert!(matches!(result, Err(Error::BadSignature(_))));
+
+    assert_eq!(
+        Archive::find_signature(&mut Cursor::new(&data), len).unwrap(),
+        Some(stub_len)
+    );
+    let options = ReaderOptions::default().with_signature_search_limit(stub_len + 1);
+    let archive = Archive::read_with_options(&mut Cursor::new(&data), len, &[], options).unwrap();
+    assert_eq!(archive.signature_offset, stub_len);
+
+    let read_all = |source: Cursor<&Vec<u8>>, options| {
+        let len = source.get_ref().len() as u64;
+        let mut sz = SevenZReader::new_with_options(source, len, Password::empty(), options)?;
+        let mut contents = Vec::new();
+        sz.for_each_entries(|entry, reader| {
+            let mut content = String::new();
+            reader.read_to_string(&mut content)?;
+            contents.push((entry.name().to_string(), content));
+            Ok(true)
+        })?;
+        Ok::<_, Error>(contents)
+    };
+    let expected = read_all(Cursor::new(&archive_bytes), ReaderOptions::default()).unwrap();
+    assert_eq!(read_all(Cursor::new(&data), options).unwrap(), expected);
+}
+
+fn read_stream_contents<R: std::io::Read>(
+    sz: &mut SevenZReader<sevenz_rust::StreamInput<R>>,
+) -> Vec<(String, Vec<u8>)> {
+    let mut contents = Vec::new();
+    sz.for_each_entries(|entry, reader| {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        contents.push((entry.name().to_string(), content));
+        Ok(true)
+    })
+    .unwrap();
+    contents
+}
+
+#[test]
+fn read_archive_from_stream() {
+    use sevenz_rust::ReaderOptions;
+
+    let path = "tests/resources/two_files_with_content_lzma.7z";
+    let mut sz = SevenZReader::open(path, Password::empty()).unwrap();
+    let mut expected = Vec::new();
+    sz.for_each_entries(|entry, reader| {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        expected.push((entry.name().to_string(), content));
+        Ok(true)
+    })
+    .unwrap();
+
+    let data = read(path).unwrap();
+    // a byte slice is only `Read`
+    let mut sz = SevenZReader::from_stream(data.as_slice(), Password::empty()).unwrap();
+    let spooled = sz.spooled().unwrap();
+    assert_eq!(spooled.len(), data.len() as u64);
+    assert!(!spooled.is_in_file());
+    assert_eq!(read_stream_contents(&mut sz), expected);
+
+    let options = ReaderOptions::default().with_spool_memory_limit(16);
+    let mut sz =
+        SevenZReader::from_stream_with_options(data.as_slice(), Password::empty(), options)
+            .unwrap();
+    assert!(sz.spooled().unwrap().is_in_file());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let prefix = format!(".sevenz-spool-{}-", std::process::id());
+        let modes = std::fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().starts_with(&prefix))
+            .filter_map(|e| e.metadata().ok())
+            .map(|m| m.permissions().mode() & 0o777)
+            .collect::<Vec<_>>();
+        assert!(!modes.is_empty());
+        assert!(modes.iter().all(|mode| *mode == 0o600), "{modes:?}");
+    }
+    assert_eq!(read_stream_contents(&mut sz), expected);
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn read_header_first_archive_from_stream() {
+    use sevenz_rust::{SevenZArchiveEntry, SevenZMethod, SevenZWriter};
+    use std::io::Cursor;
+
+    let contents = vec![
+        ("a.txt".to_string(), b"header first ".repeat(5000)),
+        ("empty.txt".to_string(), Vec::new()),
+        ("b.txt".to_string(), b"b".to_vec()),
+    ];
+    for encrypt_header in [true, false] {
+        let mut sz = SevenZWriter::new_streaming(Vec::new()).unwrap();
+        sz.set_encrypt_header(encrypt_header);
+        sz.set_header_first(true);
+        for (name, content) in &contents {
+            let mut entry = SevenZArchiveEntry::new();
+            entry.name = name.clone();
+            sz.push_archive_entry(entry, Some(content.as_slice()))
+                .unwrap();
+        }
+        let data = sz.finish_streaming().unwrap();
+
+        let mut sz = SevenZReader::from_stream(data.as_slice(), Password::empty()).unwrap();
+        assert!(sz.spooled().is_none());
+        assert_eq!(read_stream_contents(&mut sz), contents);
+        // the entries were read while streaming
+        assert!(sz.for_each_entries(|_, _| Ok(true)).is_err());
+
+        let len = data.len() as u64;
+        let mut sz = SevenZReader::new(Cursor::new(data), len, Password::empty()).unwrap();
+        let mut read_back = Vec::new();
+        sz.for_each_entries(|entry, reader| {
+            let mut content = Vec::new();
+            reader.read_to_end(&mut content)?;
+            read_back.push((entry.name().to_string(), content));
+            Ok(true)
+        })
+        .unwrap();
+        assert_eq!(read_back, contents);
+    }
+
+    // the packed streams of BCJ2 are read in turn, so the data after the header is spooled
+    let mut sz = SevenZWriter::new_streaming(Vec::new()).unwrap();
+    sz.set_header_first(true);
+    sz.set_content_methods(vec![SevenZMethod::BCJ2_FILTER.into()]);
+    let exe = read("tests/resources/decompress_x86.exe").unwrap();
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "decompress_x86.exe".to_string();
+    sz.push_archive_entry(entry, Some(exe.as_slice())).unwrap();
+    let data = sz.finish_streaming().unwrap();
+    let mut sz = SevenZReader::from_stream(data.as_slice(), Password::empty()).unwrap();
+    assert!(sz.spooled().is_some());
+    assert_eq!(
+        read_stream_contents(&mut sz),
+        vec![("decompress_x86.exe".to_string(), exe)]
+    );
+}
+
+#[test]
+fn decompress_with_progress_and_cancel() {
+    use sevenz_rust::{CancellationToken, Error, Progress, ReaderOptions, SevenZArchiveEntry};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Totals {
+        started: Vec<String>,
+        finished: Vec<String>,
+        unpacked: u64,
+        packed: u64,
+    }
+    struct RecordProgress(Arc<Mutex<Totals>>, Option<CancellationToken>);
+    impl Progress for RecordProgress {
+        fn entry_started(&mut self, entry: &SevenZArchiveEntry) {
+            self.0
+                .lock()
+                .unwrap()
+                .started
+                .push(entry.name().to_string());
+        }
+        fn entry_finished(&mut self, entry: &SevenZArchiveEntry) {
+            self.0
+                .lock()
+                .unwrap()
+                .finished
+                .push(entry.name().to_string());
+        }
+        fn bytes_processed(&mut self, unpacked: u64, packed: u64) {
+            let mut totals = self.0.lock().unwrap();
+            totals.unpacked += unpacked;
+            totals.packed += packed;
+            if let Some(token) = &self.1 {
+                token.cancel();
+            }
+        }
+    }
+
+    let source = "tests/resources/two_files_with_content_lzma.7z";
+    let archive = Archive::open(source).unwrap();
+    let names: Vec<_> = archive.files.iter().map(|f| f.name().to_string()).collect();
+    let temp_dir = tempdir().unwrap();
+
+    let totals = Arc::new(Mutex::new(Totals::default()));
+    let progress = RecordProgress(totals.clone(), None);
+    let dest = temp_dir.path().join("all");
+    sevenz_rust::decompress_file_with_progress(
+        source,
+        &dest,
+        Password::empty(),
+        ReaderOptions::default(),
+        progress,
+        None,
+    )
+    .unwrap();
+    let totals = totals.lock().unwrap();
+    assert_eq!(totals.started, names);
+    assert_eq!(totals.finished, names);
+    assert_eq!(
+        totals.unpacked,
+        archive.files.iter().map(|f| f.size()).sum::<u64>()
+    );
+    assert_eq!(totals.packed, archive.pack_sizes.iter().sum::<u64>());
+
+    let token = CancellationToken::new();
+    let progress = RecordProgress(Default::default(), Some(token.clone()));
+    let dest = temp_dir.path().join("cancelled");
+    let result = sevenz_rust::decompress_file_with_progress(
+        source,
+        dest,
+        Password::empty(),
+        ReaderOptions::default(),
+        progress,
+        Some(token),
+    );
+    assert!(matches!(result, Err(Error::Cancelled)));
+
+    // the limits of the options still apply
+    let options = ReaderOptions::default().with_max_entry_count(names.len() - 1);
+    let progress = RecordProgress(Default::default(), None);
+    let dest = temp_dir.path().join("limited");
+    let result = sevenz_rust::decompress_file_with_progress(
+        source,
+        dest,
+        Password::empty(),
+        options,
+        progress,
+        None,
+    );
+    assert!(matches!(result, Err(Error::LimitExceeded(_))));
+}
+
+#[test]
+fn read_entries_with_cursor() {
+    use std::io::Read;
+
+    let source = "tests/resources/7za433_7zip_lzma2_bcj2.7z";
+    let mut expected = Vec::new();
+    SevenZReader::open(source, Password::empty())
+        .unwrap()
+        .for_each_entries(|entry, reader| {
+            let mut content = Vec::new();
+            reader.read_to_end(&mut content)?;
+            expected.push((entry.name().to_string(), content));
+            Ok(true)
+        })
+        .unwrap();
+
+    let mut sz = SevenZReader::open(source, Password::empty()).unwrap();
+    let mut entries = sz.entries();
+    let mut read = Vec::new();
+    while let Some((entry, mut reader)) = entries.next_entry().unwrap() {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).unwrap();
+        read.push((entry.name().to_string(), content));
+    }
+    assert!(entries.next_entry().unwrap().is_none());
+    assert_eq!(read, expected);
+
+    // the first entry of the solid block is skipped, the second one partially read
+    let mut sz = SevenZReader::open(source, Password::empty()).unwrap();
+    let mut entries = sz.entries();
+    let mut read = Vec::new();
+    let mut index = 0;
+    while let Some((entry, mut reader)) = entries.next_entry().unwrap() {
+        let mut content = Vec::new();
+        match index {
+            0 => {}
+            1 => {
+                let mut buf = [0; 10];
+                reader.read_exact(&mut buf).unwrap();
+            }
+            _ => {
+                reader.read_to_end(&mut content).unwrap();
+                read.push((entry.name().to_string(), content));
+            }
+        }
+        index += 1;
+    }
+    assert_eq!(read, expected[2..]);
+
+    // two archives read side by side
+    let mut first = SevenZReader::open(source, Password::empty()).unwrap();
+    let mut second = SevenZReader::open(source, Password::empty()).unwrap();
+    let (mut first, mut second) = (first.entries(), second.entries());
+    let mut count = 0;
+    while let Some((entry, mut reader)) = first.next_entry().unwrap() {
+        let (other, mut other_reader) = second.next_entry().unwrap().unwrap();
+        assert_eq!(entry.name(), other.name());
+        let (mut content, mut other_content) = (Vec::new(), Vec::new());
+        reader.read_to_end(&mut content).unwrap();
+        other_reader.read_to_end(&mut other_content).unwrap();
+        assert_eq!(content, other_content);
+        count += 1;
+    }
+    assert_eq!(count, expected.len());
+    assert!(second.next_entry().unwrap().is_none());
+}
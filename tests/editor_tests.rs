@@ -0,0 +1,149 @@
+#![cfg(feature = "compress")]
+use std::{collections::HashMap, path::Path};
+
+use sevenz_rust::*;
+use tempfile::tempdir;
+
+fn read_all_entries(path: &Path) -> HashMap<String, Vec<u8>> {
+    let mut contents = HashMap::new();
+    let mut sz = SevenZReader::open(path, Password::empty()).unwrap();
+    sz.for_each_entries(|entry, reader| {
+        let mut content = Vec::new();
+        std::io::copy(reader, &mut content)?;
+        contents.insert(entry.name().to_string(), content);
+        Ok(true)
+    })
+    .unwrap();
+    contents
+}
+
+fn edit(src: &Path, dest: &Path, f: impl FnOnce(&mut ArchiveEditor<std::fs::File>)) {
+    let mut editor = ArchiveEditor::open(src, Password::empty()).unwrap();
+    f(&mut editor);
+    let mut sz = SevenZWriter::create(dest).unwrap();
+    editor.write_to(&mut sz).unwrap();
+    sz.finish().unwrap();
+}
+
+#[test]
+fn remove_and_rename_entries() {
+    let temp_dir = tempdir().unwrap();
+    let folder = temp_dir.path().join("folder");
+    std::fs::create_dir_all(folder.join("dir")).unwrap();
+    std::fs::write(folder.join("dir/file1.txt"), "file1 with content").unwrap();
+    std::fs::write(folder.join("dir/file2.txt"), "file2 with content").unwrap();
+    std::fs::write(folder.join("file3.txt"), "file3 with content").unwrap();
+    let src = temp_dir.path().join("src.7z");
+    let mut sz = SevenZWriter::create(&src).unwrap();
+    sz.push_source_path(&folder, |_| true).unwrap();
+    let mut entry = SevenZArchiveEntry::new();
+    entry.name = "file4.txt".to_string();
+    sz.push_archive_entry(entry, Some("file4 with content".as_bytes()))
+        .unwrap();
+    sz.finish().unwrap();
+
+    let dest = temp_dir.path().join("dest.7z");
+    edit(&src, &dest, |editor| {
+        editor.remove("dir/file1.txt").unwrap();
+        editor.rename("file4.txt", "renamed.txt").unwrap();
+        editor.rename("dir", "moved").unwrap();
+        assert!(editor.remove("missing.txt").is_err());
+        // renamed entries may not take the name of another entry
+        assert!(editor.rename("file3.txt", "renamed.txt").is_err());
+        assert!(editor.rename("file3.txt", "moved/file2.txt").is_err());
+    });
+
+    let archive = Archive::open(&dest).unwrap();
+    assert_eq!(archive.folders.len(), 2);
+    let contents = read_all_entries(&dest);
+    assert_eq!(contents.len(), 3);
+    assert_eq!(contents["moved/file2.txt"], b"file2 with content");
+    assert_eq!(contents["file3.txt"], b"file3 with content");
+    assert_eq!(contents["renamed.txt"], b"file4 with content");
+
+    let dest2 = temp_dir.path().join("dest2.7z");
+    edit(&dest, &dest2, |editor| {
+        editor.remove("moved").unwrap();
+    });
+    let contents = read_all_entries(&dest2);
+    assert_eq!(contents.len(), 2);
+    assert_eq!(contents["file3.txt"], b"file3 with content");
+    assert_eq!(contents["renamed.txt"], b"file4 with content");
+}
+
+#[test]
+fn rename_copies_multi_stream_folders() {
+    let temp_dir = tempdir().unwrap();
+    let src = Path::new("tests/resources/7za433_7zip_lzma2_bcj2.7z");
+    let before = read_all_entries(src);
+    let name = "7za433_7zip_lzma2_bcj2/bin/7za.exe";
+
+    let dest = temp_dir.path().join("dest.7z");
+    edit(src, &dest, |editor| {
+        editor.rename(name, "renamed").unwrap();
+    });
+
+    let src_archive = Archive::open(src).unwrap();
+    let dest_archive = Archive::open(&dest).unwrap();
+    assert_eq!(dest_archive.pack_sizes, src_archive.pack_sizes);
+    let after = read_all_entries(&dest);
+    assert_eq!(after.len(), before.len());
+    for (entry_name, content) in before {
+        let entry_name = if entry_name == name {
+            "renamed".to_string()
+        } else {
+            entry_name
+        };
+        assert_eq!(after[&entry_name], content);
+    }
+}
+
+#[cfg(feature = "aes256")]
+#[test]
+fn edit_encrypted_archive() {
+    let temp_dir = tempdir().unwrap();
+    let folder = temp_dir.path().join("folder");
+    std::fs::create_dir_all(&folder).unwrap();
+    std::fs::write(folder.join("file1.txt"), "file1 with content").unwrap();
+    std::fs::write(folder.join("file2.txt"), "file2 with content").unwrap();
+    let methods = || {
+        vec![
+            AesEncoderOptions::new("secret".into()).into(),
+            SevenZMethod::LZMA2.into(),
+        ]
+    };
+    let src = temp_dir.path().join("src.7z");
+    let mut sz = SevenZWriter::create(&src).unwrap();
+    sz.set_content_methods(methods());
+    sz.push_source_path(&folder, |_| true).unwrap();
+    sz.finish().unwrap();
+
+    // the solid block loses an entry, it must not be written back unencrypted
+    let dest = temp_dir.path().join("dest.7z");
+    let mut editor = ArchiveEditor::open(&src, "secret".into()).unwrap();
+    editor.remove("file1.txt").unwrap();
+    let mut sz = SevenZWriter::create(&dest).unwrap();
+    assert!(editor.write_to(&mut sz).is_err());
+
+    let mut editor = ArchiveEditor::open(&src, "secret".into()).unwrap();
+    editor.remove("file1.txt").unwrap();
+    let mut sz = SevenZWriter::create(&dest).unwrap();
+    sz.set_content_methods(methods());
+    editor.write_to(&mut sz).unwrap();
+    sz.finish().unwrap();
+
+    let archive = Archive::open_with_password(&dest, &"secret".into()).unwrap();
+    let coders = &archive.folders[0].coders;
+    assert!(coders
+        .iter()
+        .any(|c| c.decompression_method_id() == SevenZMethod::ID_AES256SHA256));
+    let mut sz = SevenZReader::open(&dest, "secret".into()).unwrap();
+    let mut contents = HashMap::new();
+    sz.for_each_entries(|entry, reader| {
+        contents.insert(entry.name().to_string(), std::io::read_to_string(reader)?);
+        Ok(true)
+    })
+    .unwrap();
+    assert_eq!(contents.len(), 1);
+    assert_eq!(contents["file2.txt"], "file2 with content");
+}
@@ -0,0 +1,130 @@
+use std::path::Path;
+
+use sevenz_rust::*;
+use tempfile::tempdir;
+
+#[test]
+fn normalize_entry_names() {
+    let dest = Path::new("dest");
+    for (name, expected) in [
+        ("dir/file.txt", "dest/dir/file.txt"),
+        ("/abs/file.txt", "dest/abs/file.txt"),
+        ("dir\\file.txt", "dest/dir/file.txt"),
+        ("C:\\dir\\file.txt", "dest/dir/file.txt"),
+        ("./dir/../file.txt", "dest/file.txt"),
+    ] {
+        assert_eq!(safe_entry_path(dest, name).unwrap(), Path::new(expected));
+    }
+    for name in [
+        "../file.txt",
+        "dir/../../file.txt",
+        "..\\file.txt",
+        "/../file.txt",
+    ] {
+        assert!(matches!(
+            safe_entry_path(dest, name),
+            Err(Error::UnsafeEntryPath(..))
+        ));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn refuse_symlinks_leaving_dest() {
+    let temp_dir = tempdir().unwrap();
+    let dest = temp_dir.path().join("dest");
+    let outside = temp_dir.path().join("outside");
+    std::fs::create_dir_all(dest.join("inside")).unwrap();
+    std::fs::create_dir(&outside).unwrap();
+    std::os::unix::fs::symlink(&outside, dest.join("out_link")).unwrap();
+    std::os::unix::fs::symlink(dest.join("inside"), dest.join("in_link")).unwrap();
+
+    assert!(matches!(
+        safe_entry_path(&dest, "out_link/file.txt"),
+        Err(Error::UnsafeEntryPath(..))
+    ));
+    assert_eq!(
+        safe_entry_path(&dest, "in_link/file.txt").unwrap(),
+        dest.join("in_link/file.txt")
+    );
+}
+
+#[cfg(feature = "compress")]
+#[test]
+fn decompress_rejects_path_traversal() {
+    let temp_dir = tempdir().unwrap();
+    let archive = temp_dir.path().join("evil.7z");
+    let mut sz = SevenZWriter::create(&archive).unwrap();
+    for name in ["/abs.txt", "dir\\file.txt", "../evil.txt"] {
+        let mut entry = SevenZArchiveEntry::new();
+        entry.name = name.to_string();
+        sz.push_archive_entry(entry, Some(name.as_bytes())).unwrap();
+    }
+    sz.finish().unwrap();
+
+    let dest = temp_dir.path().join("dest");
+    let result = decompress_file(&archive, &dest);
+    assert!(matches!(
+        result,
+        Err(Error::UnsafeEntryPath(name, _)) if name == "../evil.txt"
+    ));
+    assert!(!temp_dir.path().join("evil.txt").exists());
+    assert_eq!(
+        std::fs::read_to_string(dest.join("abs.txt")).unwrap(),
+        "/abs.txt"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dest.join("dir").join("file.txt")).unwrap(),
+        "dir\\file.txt"
+    );
+}
+
+#[cfg(all(feature = "compress", unix))]
+#[test]
+fn decompress_rejects_symlinks_leaving_dest() {
+    let temp_dir = tempdir().unwrap();
+    for target in ["../outside", "/etc/passwd", "dir/../../outside"] {
+        let archive = temp_dir.path().join("evil.7z");
+        let mut sz = SevenZWriter::create(&archive).unwrap();
+        let mut entry = SevenZArchiveEntry::new();
+        entry.name = "link".to_string();
+        entry.set_unix_mode(0o120777);
+        sz.push_archive_entry(entry, Some(target.as_bytes()))
+            .unwrap();
+        sz.finish().unwrap();
+
+        let dest = temp_dir.path().join("dest");
+        let result = decompress_file(&archive, &dest);
+        assert!(
+            matches!(result, Err(Error::UnsafeEntryPath(..))),
+            "{target}"
+        );
+        assert!(dest.join("link").symlink_metadata().is_err());
+    }
+}
+
+#[cfg(all(feature = "compress", unix))]
+#[test]
+fn decompress_rejects_symlinks_leaving_dest_through_other_links() {
+    let temp_dir = tempdir().unwrap();
+    let archive = temp_dir.path().join("chained.7z");
+    let mut sz = SevenZWriter::create(&archive).unwrap();
+    for (name, target) in [("p", "."), ("inside", "p/file.txt"), ("x", "p/../outside")] {
+        let mut entry = SevenZArchiveEntry::new();
+        entry.name = name.to_string();
+        entry.set_unix_mode(0o120777);
+        sz.push_archive_entry(entry, Some(target.as_bytes()))
+            .unwrap();
+    }
+    sz.finish().unwrap();
+
+    // `x` looks like it stays in dest, but `p/..` is the parent of dest
+    let dest = temp_dir.path().join("dest");
+    let result = decompress_file(&archive, &dest);
+    assert!(matches!(result, Err(Error::UnsafeEntryPath(name, _)) if name == "x"));
+    assert_eq!(
+        std::fs::read_link(dest.join("inside")).unwrap(),
+        Path::new("p/file.txt")
+    );
+    assert!(dest.join("x").symlink_metadata().is_err());
+}
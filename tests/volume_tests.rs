@@ -0,0 +1,80 @@
+#![cfg(feature = "compress")]
+use std::io::Cursor;
+
+use sevenz_rust::*;
+use tempfile::tempdir;
+
+fn pseudo_random_bytes(len: usize, mut seed: u32) -> Vec<u8> {
+    (0..len)
+        .map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn write_and_read_volumes() {
+    let temp_dir = tempdir().unwrap();
+    let folder = temp_dir.path().join("folder");
+    std::fs::create_dir(&folder).unwrap();
+    let contents = [
+        ("a.bin", pseudo_random_bytes(150_000, 1)),
+        ("b.bin", pseudo_random_bytes(70_000, 2)),
+        ("c.txt", b"small".to_vec()),
+    ];
+    for (name, content) in &contents {
+        std::fs::write(folder.join(name), content).unwrap();
+    }
+
+    let base = temp_dir.path().join("archive.7z");
+    // stale volumes of an earlier, larger archive must not be picked up
+    for i in 0..8 {
+        std::fs::write(volume_path(&base, i), b"stale").unwrap();
+    }
+    let volume_size = 64 * 1024;
+    let mut sz = SevenZWriter::create_volumes(&base, volume_size).unwrap();
+    sz.push_source_path(&folder, |_| true).unwrap();
+    let writer = sz.finish().unwrap();
+    let volume_count = writer.volume_count();
+    assert!(volume_count >= 4);
+    for i in 0..volume_count {
+        let len = std::fs::metadata(volume_path(&base, i)).unwrap().len();
+        if i + 1 < volume_count {
+            assert_eq!(len, volume_size);
+        } else {
+            assert!(len > 0 && len <= volume_size);
+        }
+    }
+    assert!(!volume_path(&base, volume_count).exists());
+
+    let mut sz = SevenZReader::open_volumes(volume_path(&base, 0), Password::empty()).unwrap();
+    let mut read = Vec::new();
+    sz.for_each_entries(|entry, reader| {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        read.push((entry.name().to_string(), content));
+        Ok(true)
+    })
+    .unwrap();
+    read.sort();
+    for ((name, content), (read_name, read_content)) in contents.iter().zip(&read) {
+        assert_eq!(name, read_name);
+        assert!(content == read_content, "content of {name}");
+    }
+
+    // the same archive from in-memory volumes of different sizes
+    let mut whole = Vec::new();
+    for i in 0..volume_count {
+        whole.extend(std::fs::read(volume_path(&base, i)).unwrap());
+    }
+    let volumes = whole
+        .chunks(10_000)
+        .map(|chunk| Cursor::new(chunk.to_vec()))
+        .collect();
+    let mut volumes = MultiVolumeReader::new(volumes).unwrap();
+    assert_eq!(volumes.len(), whole.len() as u64);
+    let len = volumes.len();
+    let archive = Archive::read(&mut volumes, len, &[]).unwrap();
+    assert_eq!(archive.files.len(), contents.len());
+}